@@ -0,0 +1,116 @@
+//! `#[concurrent_test]`, the attribute form of `rust_atomics::concurrent_test!`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{Error, ItemFn, Meta, ReturnType, Token, parse_macro_input};
+
+/// Turns a function into a `#[test]` whose body runs directly, inside the
+/// loom model checker when built with `--cfg loom`, or under shuttle with
+/// `--cfg shuttle`.
+///
+/// Accepts the same options as `concurrent_test!`, either as `name = value`
+/// or as a bare flag such as `should_find_bug`, which are passed on to
+/// `rust_atomics::model::Builder`. Two more flags are handled here:
+///
+/// - `loom_only`: only compile the test when built with `--cfg loom`.
+/// - `std_only`: only compile the test when built with neither `--cfg loom`
+///   nor `--cfg shuttle`.
+///
+/// The function must take no arguments and return `()`, as the body is
+/// run by the model checker, which can't pass anything back.
+///
+/// ```ignore
+/// #[concurrent_test(preemption_bound = 2)]
+/// fn my_test() {
+///     // Test code here
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn concurrent_test(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args with Punctuated::<Meta, Token![,]>::parse_terminated);
+    let item = parse_macro_input!(item as ItemFn);
+
+    expand(args, item)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(args: Punctuated<Meta, Token![,]>, item: ItemFn) -> syn::Result<TokenStream2> {
+    let mut cfg = None;
+    let mut options = Vec::new();
+
+    for arg in args {
+        match arg {
+            Meta::Path(path) if path.is_ident("loom_only") || path.is_ident("std_only") => {
+                if cfg.is_some() {
+                    return Err(Error::new(
+                        path.span(),
+                        "only one of `loom_only` and `std_only` may be given",
+                    ));
+                }
+                cfg = Some(if path.is_ident("loom_only") {
+                    quote!(#[cfg(loom)])
+                } else {
+                    quote!(#[cfg(not(any(loom, shuttle)))])
+                });
+            }
+            Meta::Path(path) => {
+                let Some(flag) = path.get_ident() else {
+                    return Err(Error::new(path.span(), "expected an option name"));
+                };
+                options.push(quote!(.#flag()));
+            }
+            Meta::NameValue(option) => {
+                let Some(name) = option.path.get_ident() else {
+                    return Err(Error::new(option.path.span(), "expected an option name"));
+                };
+                let value = option.value;
+                options.push(quote!(.#name(#value)));
+            }
+            other => {
+                return Err(Error::new(
+                    other.span(),
+                    "expected `loom_only`, `std_only` or `option = value`",
+                ));
+            }
+        }
+    }
+
+    let sig = &item.sig;
+    if !sig.inputs.is_empty() {
+        return Err(Error::new(
+            sig.inputs.span(),
+            "concurrent tests cannot take arguments",
+        ));
+    }
+    if let Some(asyncness) = sig.asyncness {
+        return Err(Error::new(
+            asyncness.span(),
+            "concurrent tests cannot be async",
+        ));
+    }
+    if let ReturnType::Type(..) = sig.output {
+        return Err(Error::new_spanned(
+            &sig.output,
+            "concurrent tests cannot return a value",
+        ));
+    }
+
+    let attrs = &item.attrs;
+    let vis = &item.vis;
+    let body = &item.block;
+
+    Ok(quote! {
+        #[test]
+        #cfg
+        #(#attrs)*
+        #vis #sig {
+            ::rust_atomics::model::Builder::new()
+                #(#options)*
+                .check(|| #body)
+        }
+    })
+}
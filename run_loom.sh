@@ -1,4 +0,0 @@
-#!/bin/bash
-
-RUSTFLAGS="--cfg loom" \
-cargo test --release
\ No newline at end of file
@@ -1,4 +0,0 @@
-#!/bin/bash
-
-# Run tests normally (without Loom)
-cargo test --release
@@ -0,0 +1,94 @@
+//! Where the loom workflow keeps its files.
+//!
+//! `loom-kit checkpoint` and `loom-kit trace` write a checkpoint per test to
+//! `loom_test_artifacts/<test>.json`, relative to the directory cargo runs
+//! the tests in, which is the package root. `loom-kit trace` also saves the
+//! test output next to it, as `<test>.log`, a page for stepping through the
+//! failing execution as `<test>.html`, and its happens-before graph as
+//! `<test>.dot`.
+//!
+//! With `LOOM_KIT_STATS=1`, every exploration also appends its
+//! [stats](crate::stats) to `loom_test_artifacts/stats.jsonl`.
+//!
+//! Checkpoints worth keeping are promoted into the regression corpus, under
+//! `tests/loom_corpus/<test>/`, which is committed and replayed by the test
+//! before every full exploration.
+
+use std::io;
+use std::path::PathBuf;
+
+/// The directory all loom artifacts are written to.
+pub const DIR: &str = "loom_test_artifacts";
+
+/// The directory of the regression corpus.
+pub const CORPUS_DIR: &str = "tests/loom_corpus";
+
+/// The checkpoint file for `test`.
+///
+/// Tests inside modules have `::` in their name, which is replaced with `__`
+/// so the file name stays portable.
+pub fn checkpoint_path(test: &str) -> PathBuf {
+    artifact_path(test, "json")
+}
+
+/// The trace log for `test`, named like its checkpoint.
+pub fn log_path(test: &str) -> PathBuf {
+    artifact_path(test, "log")
+}
+
+/// The happens-before graph for `test`, named like its checkpoint.
+pub fn graph_path(test: &str) -> PathBuf {
+    artifact_path(test, "dot")
+}
+
+/// The step-by-step viewer page for `test`, named like its checkpoint.
+pub fn viewer_path(test: &str) -> PathBuf {
+    artifact_path(test, "html")
+}
+
+/// The file exploration stats are appended to, shared by every test.
+pub fn stats_path() -> PathBuf {
+    PathBuf::from(DIR).join("stats.jsonl")
+}
+
+/// The directory holding the corpus checkpoints for `test`.
+pub fn corpus_dir(test: &str) -> PathBuf {
+    PathBuf::from(CORPUS_DIR).join(file_stem(test))
+}
+
+/// The corpus checkpoints for `test`, sorted by name. A test without a
+/// corpus directory has none.
+pub fn corpus_entries(test: &str) -> io::Result<Vec<PathBuf>> {
+    let dir = corpus_dir(test);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .filter(|path| {
+            path.as_ref()
+                .map_or(true, |path| path.extension().is_some_and(|e| e == "json"))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+fn artifact_path(test: &str, extension: &str) -> PathBuf {
+    PathBuf::from(DIR).join(format!("{}.{extension}", file_stem(test)))
+}
+
+fn file_stem(test: &str) -> String {
+    test.replace("::", "__")
+}
+
+/// The name of the test running on this thread, as the test harness names
+/// it, e.g. `release_and_acquire_incorrect`.
+pub fn current_test() -> Option<String> {
+    let thread = std::thread::current();
+    match thread.name() {
+        Some("main") | None => None,
+        Some(name) => Some(name.to_string()),
+    }
+}
@@ -0,0 +1,387 @@
+//! `loom-kit`: runs the test suite normally, under loom or under shuttle,
+//! manages the checkpoints and traces written to `loom_test_artifacts/`, and
+//! promotes checkpoints into the regression corpus in `tests/loom_corpus/`.
+//!
+//! Every command that runs tests passes any extra arguments on to
+//! `cargo test`, and exits with cargo's status.
+
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode, ExitStatus};
+
+use rust_atomics::artifacts;
+use rust_atomics::checkpoint::Checkpoint;
+use rust_atomics::happens_before::Graph;
+use rust_atomics::trace;
+use rust_atomics::viewer;
+
+const USAGE: &str = "\
+Usage: loom-kit <command> [args...]
+
+Commands:
+  run [cargo args...]                 Run all tests under loom
+  normal [cargo args...]              Run all tests without loom
+  shuttle [cargo args...]             Run all tests under shuttle
+  checkpoint <test> [cargo args...]   Run <test> under loom, saving a checkpoint
+  trace <test> [cargo args...]        Run <test> under loom with full tracing
+  show <test>                         Print the interleaving saved for <test>
+  report <test>                       Print a per-thread timeline of the last
+                                      `trace` of <test>, up to the failure
+  promote <test>                      Copy the checkpoint of <test> into its
+                                      regression corpus in tests/loom_corpus/
+  clear                               Remove everything in loom_test_artifacts/
+  list-artifacts                      List the files in loom_test_artifacts/
+  help                                Show this message";
+
+/// Exit status for invalid command line usage, as in `sysexits.h`.
+const EXIT_USAGE: u8 = 64;
+
+fn main() -> ExitCode {
+    let mut args = std::env::args_os().skip(1);
+    let Some(command) = args.next() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(EXIT_USAGE);
+    };
+    let rest: Vec<OsString> = args.collect();
+
+    let result = match command.to_str() {
+        Some("run") => Ok(cargo_test(&rest, Some("loom"), &[])),
+        Some("normal") => Ok(cargo_test(&rest, None, &[])),
+        Some("shuttle") => Ok(cargo_test(&rest, Some("shuttle"), &[])),
+        Some("checkpoint") => with_test_name(&rest, |test, rest| {
+            let checkpoint = prepare_checkpoint(test)?;
+            Ok(cargo_test(
+                &with_filter(test, rest),
+                Some("loom"),
+                &[
+                    ("LOOM_CHECKPOINT_INTERVAL", "1".as_ref()),
+                    ("LOOM_CHECKPOINT_FILE", checkpoint.as_os_str()),
+                ],
+            ))
+        }),
+        Some("trace") => with_test_name(&rest, |test, rest| {
+            let checkpoint = prepare_checkpoint(test)?;
+            let log = artifacts::log_path(test);
+            let command = cargo_command(
+                &with_filter(test, rest),
+                Some("loom"),
+                &[
+                    ("LOOM_LOG", "trace".as_ref()),
+                    ("LOOM_LOCATION", "1".as_ref()),
+                    ("LOOM_CHECKPOINT_INTERVAL", "1".as_ref()),
+                    ("LOOM_CHECKPOINT_FILE", checkpoint.as_os_str()),
+                    // Tests that expect a bug pass, and their output would
+                    // be swallowed
+                    ("RUST_TEST_NOCAPTURE", "1".as_ref()),
+                    ("RUST_TEST_THREADS", "1".as_ref()),
+                ],
+            );
+            let status = run_logged(command, &log)?;
+            eprintln!(
+                "trace saved to {}; run `loom-kit report {test}` for a timeline",
+                log.display()
+            );
+            write_artifacts(test, &log, &checkpoint)?;
+            Ok(status)
+        }),
+        Some("show") => with_only_test_name("show", &rest, show),
+        Some("report") => with_only_test_name("report", &rest, report),
+        Some("promote") => with_only_test_name("promote", &rest, promote),
+        Some("clear") if rest.is_empty() => clear(),
+        Some("list-artifacts") if rest.is_empty() => list_artifacts(),
+        Some(command @ ("clear" | "list-artifacts")) => {
+            eprintln!("error: `{command}` takes no arguments\n\n{USAGE}");
+            Ok(ExitCode::from(EXIT_USAGE))
+        }
+        Some("help" | "-h" | "--help") => {
+            println!("{USAGE}");
+            Ok(ExitCode::SUCCESS)
+        }
+        _ => {
+            eprintln!("error: unknown command {command:?}\n\n{USAGE}");
+            Ok(ExitCode::from(EXIT_USAGE))
+        }
+    };
+
+    result.unwrap_or_else(|error| {
+        eprintln!("error: {error}");
+        ExitCode::FAILURE
+    })
+}
+
+/// Runs `cargo test --release` with `args`, built with `--cfg <backend>` if
+/// a backend is given.
+fn cargo_test(args: &[OsString], backend: Option<&str>, env: &[(&str, &OsStr)]) -> ExitCode {
+    exit_code(cargo_command(args, backend, env).status())
+}
+
+fn cargo_command(args: &[OsString], backend: Option<&str>, env: &[(&str, &OsStr)]) -> Command {
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut command = Command::new(cargo);
+    command
+        .args(["test", "--release"])
+        .args(args)
+        .envs(env.iter().copied());
+
+    if let Some(backend) = backend {
+        // Keep any flags the caller already set
+        let mut rustflags = std::env::var_os("RUSTFLAGS").unwrap_or_default();
+        if !rustflags.is_empty() {
+            rustflags.push(" ");
+        }
+        rustflags.push(format!("--cfg {backend}"));
+        command.env("RUSTFLAGS", rustflags);
+    }
+    command
+}
+
+/// Runs `command`, copying everything it prints to `log` as well.
+fn run_logged(mut command: Command, log: &Path) -> io::Result<ExitCode> {
+    let mut file = fs::File::create(log)?;
+    // One pipe for both streams keeps panics in order with the trace
+    let (mut reader, writer) = io::pipe()?;
+    command.stdout(writer.try_clone()?).stderr(writer);
+    let child = command.spawn();
+    // The command holds the write ends; reading only ends once they close
+    drop(command);
+    let mut child = match child {
+        Ok(child) => child,
+        Err(error) => return Ok(exit_code(Err(error))),
+    };
+
+    let mut stdout = io::stdout();
+    let mut buf = [0; 8192];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        stdout.write_all(&buf[..n])?;
+        file.write_all(&buf[..n])?;
+    }
+    Ok(exit_code(child.wait()))
+}
+
+fn exit_code(status: io::Result<ExitStatus>) -> ExitCode {
+    match status {
+        // A test failure is cargo's status 101; pass it on unchanged
+        Ok(status) => match status.code() {
+            Some(0) => ExitCode::SUCCESS,
+            Some(code) => ExitCode::from(u8::try_from(code).unwrap_or(1)),
+            None => ExitCode::FAILURE,
+        },
+        Err(error) => {
+            eprintln!("error: failed to run cargo: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Checks that `args` starts with a usable test name and runs `f` with it.
+fn with_test_name(
+    args: &[OsString],
+    f: impl FnOnce(&str, &[OsString]) -> io::Result<ExitCode>,
+) -> io::Result<ExitCode> {
+    let Some((test, rest)) = args.split_first() else {
+        eprintln!("error: a test name is required\n\n{USAGE}");
+        return Ok(ExitCode::from(EXIT_USAGE));
+    };
+
+    // The name becomes a file name inside the artifacts directory
+    match test.to_str() {
+        Some(test)
+            if !test.is_empty()
+                && !test.starts_with('-')
+                && !test.contains(['/', '\\'])
+                && test != "."
+                && test != ".." =>
+        {
+            f(test, rest)
+        }
+        _ => {
+            eprintln!("error: invalid test name {test:?}");
+            Ok(ExitCode::from(EXIT_USAGE))
+        }
+    }
+}
+
+/// Like [`with_test_name`], for commands that take nothing else.
+fn with_only_test_name(
+    command: &str,
+    args: &[OsString],
+    f: impl FnOnce(&str) -> io::Result<ExitCode>,
+) -> io::Result<ExitCode> {
+    with_test_name(args, |test, rest| {
+        if !rest.is_empty() {
+            eprintln!("error: `{command}` takes only a test name\n\n{USAGE}");
+            return Ok(ExitCode::from(EXIT_USAGE));
+        }
+        f(test)
+    })
+}
+
+/// Puts the test name filter in front of the extra cargo arguments, and
+/// makes it match that test alone rather than every test whose name
+/// contains it, as they would all write the same checkpoint.
+fn with_filter(test: &str, rest: &[OsString]) -> Vec<OsString> {
+    let mut args: Vec<OsString> = std::iter::once(test.into())
+        .chain(rest.iter().cloned())
+        .collect();
+    if !rest.iter().any(|arg| arg == "--") {
+        args.push("--".into());
+    }
+    args.push("--exact".into());
+    args
+}
+
+/// Creates the artifacts directory and returns the checkpoint path for `test`.
+fn prepare_checkpoint(test: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(artifacts::DIR)?;
+    Ok(artifacts::checkpoint_path(test))
+}
+
+fn show(test: &str) -> io::Result<ExitCode> {
+    let path = artifacts::checkpoint_path(test);
+    if !path.exists() {
+        eprintln!("error: no checkpoint for {test}; run `loom-kit checkpoint {test}` first");
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let checkpoint = Checkpoint::load(&path)
+        .map_err(|error| io::Error::new(error.kind(), format!("{}: {error}", path.display())))?;
+    println!("{}", path.display());
+    print!("{checkpoint}");
+    Ok(ExitCode::SUCCESS)
+}
+
+fn report(test: &str) -> io::Result<ExitCode> {
+    let path = artifacts::log_path(test);
+    if !path.exists() {
+        eprintln!("error: no trace for {test}; run `loom-kit trace {test}` first");
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let executions = trace::parse(&fs::read_to_string(&path)?);
+    let Some(execution) = trace::failing(&executions) else {
+        eprintln!("error: {} has no loom executions in it", path.display());
+        return Ok(ExitCode::FAILURE);
+    };
+
+    println!("{}", path.display());
+    if execution.panic.is_none() {
+        println!("no execution panicked; showing the last one");
+    }
+    print!("{}", execution.timeline());
+    Ok(ExitCode::SUCCESS)
+}
+
+fn promote(test: &str) -> io::Result<ExitCode> {
+    let checkpoint = artifacts::checkpoint_path(test);
+    if !checkpoint.exists() {
+        eprintln!("error: no checkpoint for {test}; run `loom-kit checkpoint {test}` first");
+        return Ok(ExitCode::FAILURE);
+    }
+    // Don't commit something the test can't replay
+    let contents = fs::read(&checkpoint)?;
+    Checkpoint::load(&checkpoint).map_err(|error| {
+        io::Error::new(error.kind(), format!("{}: {error}", checkpoint.display()))
+    })?;
+
+    let entries = artifacts::corpus_entries(test)?;
+    for entry in &entries {
+        if fs::read(entry)? == contents {
+            println!("{} is already in the corpus", entry.display());
+            return Ok(ExitCode::SUCCESS);
+        }
+    }
+
+    // Numbered so entries replay in the order they were promoted
+    let next = entries
+        .iter()
+        .filter_map(|entry| entry.file_stem()?.to_str()?.parse::<usize>().ok())
+        .max()
+        .map_or(1, |last| last + 1);
+    let dir = artifacts::corpus_dir(test);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{next:03}.json"));
+    fs::write(&path, contents)?;
+    println!(
+        "saved {} to {}; commit it and {test} replays that interleaving before exploring",
+        checkpoint.display(),
+        path.display()
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Saves a viewer page for the execution in `log` that panicked, or the last
+/// one, along with the happens-before graph if it panicked.
+fn write_artifacts(test: &str, log: &Path, checkpoint: &Path) -> io::Result<()> {
+    let executions = trace::parse(&fs::read_to_string(log)?);
+    let Some(execution) = trace::failing(&executions) else {
+        return Ok(());
+    };
+
+    // Loom saved the checkpoint just before running this execution
+    let checkpoint = Checkpoint::load(checkpoint).ok();
+    let path = artifacts::viewer_path(test);
+    fs::write(&path, viewer::render(test, execution, checkpoint.as_ref()))?;
+    eprintln!("step-by-step viewer saved to {}", path.display());
+
+    if execution.panic.is_some() {
+        let graph = Graph::build(execution, checkpoint.as_ref());
+        let path = artifacts::graph_path(test);
+        fs::write(&path, graph.to_dot())?;
+        eprintln!(
+            "happens-before graph saved to {}; render it with `dot -Tsvg -O {0}`",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+fn clear() -> io::Result<ExitCode> {
+    let dir = Path::new(artifacts::DIR);
+    if !dir.exists() {
+        println!("No loom artifacts directory found. Creating empty directory...");
+        fs::create_dir_all(dir)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    println!("Clearing loom test artifacts...");
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+    println!("Done.");
+    Ok(ExitCode::SUCCESS)
+}
+
+fn list_artifacts() -> io::Result<ExitCode> {
+    let dir = Path::new(artifacts::DIR);
+    if !dir.exists() {
+        println!("No loom artifacts directory found.");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| {
+            let entry = entry?;
+            Ok((entry.file_name(), entry.metadata()?.len()))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    if entries.is_empty() {
+        println!("No loom artifacts.");
+    }
+    for (name, len) in entries {
+        println!("{:>10}  {}", len, name.to_string_lossy());
+    }
+    Ok(ExitCode::SUCCESS)
+}
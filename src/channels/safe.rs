@@ -1,11 +1,19 @@
+use crate::sync::watchdog::wait_point;
 use crate::sync::{Condvar, Mutex};
 use std::collections::VecDeque;
+use std::panic::Location;
 
 pub struct SafeChannel<T> {
     messages: Mutex<VecDeque<T>>,
     item_ready: Condvar,
 }
 
+impl<T> Default for SafeChannel<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> SafeChannel<T> {
     pub fn new() -> Self {
         Self {
@@ -19,12 +27,16 @@ impl<T> SafeChannel<T> {
         self.item_ready.notify_one();
     }
 
+    #[track_caller]
     pub fn receive(&self) -> T {
+        let caller = Location::caller();
+        let mut waiting = None;
         let mut m = self.messages.lock().unwrap();
         loop {
             if let Some(message) = m.pop_front() {
                 return message;
             }
+            waiting.get_or_insert_with(|| wait_point("SafeChannel::receive", caller));
             m = self.item_ready.wait(m).unwrap();
         }
     }
@@ -8,6 +8,12 @@ pub struct UnsafeOneShotChannel<T> {
 
 unsafe impl<T> Sync for UnsafeOneShotChannel<T> where T: Send {}
 
+impl<T> Default for UnsafeOneShotChannel<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> UnsafeOneShotChannel<T> {
     pub fn new() -> Self {
         Self {
@@ -16,6 +22,9 @@ impl<T> UnsafeOneShotChannel<T> {
         }
     }
 
+    /// # Safety
+    ///
+    /// Must be called at most once, and never concurrently with `receive`.
     pub unsafe fn send(&self, message: T) {
         let message_holder = unsafe { self.message.get_mut_ext() };
         message_holder.write(message);
@@ -26,6 +35,9 @@ impl<T> UnsafeOneShotChannel<T> {
         self.ready.load(atomic::Ordering::Acquire)
     }
 
+    /// # Safety
+    ///
+    /// Must be called at most once, and only after `is_ready` returned `true`.
     pub unsafe fn receive(&self) -> T {
         unsafe {
             let message_holder = self.message.get_mut_ext();
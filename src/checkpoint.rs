@@ -0,0 +1,345 @@
+//! Reads the checkpoint files loom writes, e.g. the ones `loom-kit
+//! checkpoint` leaves in `loom_test_artifacts/`.
+//!
+//! A checkpoint is loom's exploration path: the list of branch points the
+//! current execution went through, in order. Each one is either a
+//! scheduling decision (which thread runs next), the choice of which store
+//! an atomic load reads from, or whether a wait wakes up spuriously. The
+//! path is the interleaving that runs when the checkpoint is replayed.
+//!
+//! Nothing here needs `--cfg loom`, so the files can be inspected from any
+//! build, including `loom-kit` itself.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// A parsed checkpoint file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Checkpoint {
+    /// The preemption bound the exploration ran with, if any.
+    pub preemption_bound: Option<u8>,
+    /// Every branch point on the path, in the order they were reached.
+    #[serde(deserialize_with = "entries")]
+    pub branches: Vec<Branch>,
+}
+
+/// A single branch point on the path.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum Branch {
+    Schedule(Schedule),
+    Load(Load),
+    Spurious(Spurious),
+}
+
+/// A point where loom picked the thread to run next.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Schedule {
+    /// Preemptions on the path before this point.
+    pub preemptions: u8,
+    /// The thread that was running when this point was reached, if it was
+    /// still able to carry on. Scheduling any other thread preempts it.
+    pub initial_active: Option<u8>,
+    /// The state of each thread slot, indexed by loom's thread id. Thread
+    /// 0 is the one the model started on.
+    pub threads: Vec<ThreadState>,
+    /// Index in [`Checkpoint::branches`] of the previous scheduling point.
+    #[serde(deserialize_with = "prev")]
+    pub prev: Option<usize>,
+}
+
+/// Where a thread stands at a scheduling point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ThreadState {
+    /// Not runnable: not spawned yet, blocked or finished.
+    Disabled,
+    /// Runnable, but not worth exploring from here.
+    Skip,
+    /// Runnable, but yielded.
+    Yield,
+    /// Runnable and still to be explored from here.
+    Pending,
+    /// The thread scheduled at this point on the current path.
+    Active,
+    /// Already explored from here.
+    Visited,
+}
+
+/// A point where an atomic load picked the store it reads from.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawLoad")]
+pub struct Load {
+    /// Every store the load may read, as indices into the atomic's history.
+    pub values: Vec<u8>,
+    /// Index into `values` of the store read on the current path.
+    pub pos: usize,
+}
+
+/// A point where a wait could wake up spuriously.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Spurious {
+    /// Whether the wait wakes up spuriously on the current path.
+    #[serde(rename = "spur")]
+    pub spurious: bool,
+}
+
+/// A scheduling point where a thread was switched out while it could still
+/// have carried on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Preemption {
+    /// The scheduling step, counting only [`Branch::Schedule`] points.
+    pub step: usize,
+    /// The thread that was switched out.
+    pub preempted: usize,
+    /// The thread that ran instead.
+    pub by: usize,
+}
+
+impl Checkpoint {
+    /// Reads and parses the checkpoint at `path`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        Ok(json.parse()?)
+    }
+
+    /// The number of branch points on the path, of any kind.
+    pub fn branch_count(&self) -> usize {
+        self.branches.len()
+    }
+
+    /// The scheduling points on the path, in order. The position in this
+    /// iterator is the "step" the other queries take.
+    pub fn schedules(&self) -> impl Iterator<Item = &Schedule> {
+        self.branches.iter().filter_map(|branch| match branch {
+            Branch::Schedule(schedule) => Some(schedule),
+            _ => None,
+        })
+    }
+
+    /// The atomic load choices on the path, in order.
+    pub fn loads(&self) -> impl Iterator<Item = &Load> {
+        self.branches.iter().filter_map(|branch| match branch {
+            Branch::Load(load) => Some(load),
+            _ => None,
+        })
+    }
+
+    /// The thread scheduled at each step, i.e. the interleaving itself.
+    pub fn schedule_path(&self) -> Vec<Option<usize>> {
+        self.schedules().map(Schedule::active).collect()
+    }
+
+    /// The thread scheduled at `step`.
+    pub fn thread_at(&self, step: usize) -> Option<usize> {
+        self.schedules().nth(step)?.active()
+    }
+
+    /// The thread that was preempted at `step`, if a preemption happened
+    /// there.
+    pub fn preempted_at(&self, step: usize) -> Option<usize> {
+        self.schedules().nth(step)?.preempted()
+    }
+
+    /// Every preemption on the path, in order.
+    pub fn preemptions(&self) -> Vec<Preemption> {
+        self.schedules()
+            .enumerate()
+            .filter_map(|(step, schedule)| {
+                Some(Preemption {
+                    step,
+                    preempted: schedule.preempted()?,
+                    by: schedule.active()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Roughly how far through the whole exploration this path is, from 0
+    /// to 1.
+    ///
+    /// Loom explores depth-first, trying the options at each branch point in
+    /// turn, so the options already done at every point on the path tell how
+    /// much of the tree lies behind it. The estimate assumes the subtrees
+    /// under every option are the same size, which they rarely are, and
+    /// loom only adds options to a point once a later execution shows they
+    /// matter, so early on it tends to be too high.
+    pub fn progress(&self) -> f64 {
+        let mut progress = 0.0;
+        let mut share = 1.0;
+        for branch in &self.branches {
+            let (done, options) = match branch {
+                Branch::Schedule(schedule) => {
+                    let count = |wanted| {
+                        schedule
+                            .threads
+                            .iter()
+                            .filter(|&&state| state == wanted)
+                            .count()
+                    };
+                    if schedule.active().is_none() {
+                        continue;
+                    }
+                    let visited = count(ThreadState::Visited);
+                    (visited, visited + 1 + count(ThreadState::Pending))
+                }
+                Branch::Load(load) => (load.pos, load.values.len()),
+                Branch::Spurious(spurious) => (usize::from(spurious.spurious), 2),
+            };
+            share /= options as f64;
+            progress += done as f64 * share;
+        }
+        progress
+    }
+
+    /// The number of threads that show up anywhere on the path.
+    pub fn thread_count(&self) -> usize {
+        self.schedules()
+            .filter_map(|schedule| {
+                schedule
+                    .threads
+                    .iter()
+                    .rposition(|state| *state != ThreadState::Disabled)
+            })
+            .max()
+            .map_or(0, |last| last + 1)
+    }
+}
+
+impl FromStr for Checkpoint {
+    type Err = serde_json::Error;
+
+    fn from_str(json: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(json)
+    }
+}
+
+impl Schedule {
+    /// The thread scheduled at this point.
+    pub fn active(&self) -> Option<usize> {
+        self.threads
+            .iter()
+            .position(|state| *state == ThreadState::Active)
+    }
+
+    /// The thread switched out at this point, if scheduling
+    /// [`active`](Self::active) preempted it.
+    pub fn preempted(&self) -> Option<usize> {
+        let initial = usize::from(self.initial_active?);
+        (self.active() != Some(initial)).then_some(initial)
+    }
+}
+
+impl Load {
+    /// The store read on the current path, as an index into the atomic's
+    /// history.
+    pub fn chosen(&self) -> u8 {
+        self.values[self.pos]
+    }
+}
+
+impl fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let preemptions = self.preemptions();
+        writeln!(
+            f,
+            "{} branches, {} threads, {} preemptions (bound: {})",
+            self.branch_count(),
+            self.thread_count(),
+            preemptions.len(),
+            self.preemption_bound
+                .map_or_else(|| "none".to_string(), |bound| bound.to_string()),
+        )?;
+
+        let mut step = 0;
+        for (index, branch) in self.branches.iter().enumerate() {
+            write!(f, "{index:>4}  ")?;
+            match branch {
+                Branch::Schedule(schedule) => {
+                    write!(f, "step {step:>3}: ")?;
+                    match schedule.active() {
+                        Some(thread) => write!(f, "thread {thread}")?,
+                        None => write!(f, "no thread")?,
+                    }
+                    if let Some(preempted) = schedule.preempted() {
+                        write!(f, " (preempts thread {preempted})")?;
+                    }
+                    step += 1;
+                }
+                Branch::Load(load) => write!(
+                    f,
+                    "load reads store {} (choice {} of {})",
+                    load.chosen(),
+                    load.pos + 1,
+                    load.values.len()
+                )?,
+                Branch::Spurious(spurious) => write!(
+                    f,
+                    "wait {}",
+                    if spurious.spurious {
+                        "wakes spuriously"
+                    } else {
+                        "blocks"
+                    }
+                )?,
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Loom stores the branches in an object store, `{"entries": [...]}`.
+fn entries<'de, D>(deserializer: D) -> Result<Vec<Branch>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Store {
+        entries: Vec<Branch>,
+    }
+
+    Ok(Store::deserialize(deserializer)?.entries)
+}
+
+/// Loom stores links between branches as `{"index": n, "_p": null}`.
+fn prev<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Ref {
+        index: usize,
+    }
+
+    Ok(Option::<Ref>::deserialize(deserializer)?.map(|prev| prev.index))
+}
+
+/// A load as loom stores it, in a fixed-size array with a separate length.
+#[derive(Deserialize)]
+struct RawLoad {
+    values: Vec<u8>,
+    pos: u8,
+    len: u8,
+}
+
+impl TryFrom<RawLoad> for Load {
+    type Error = String;
+
+    fn try_from(raw: RawLoad) -> Result<Self, Self::Error> {
+        let (len, pos) = (usize::from(raw.len), usize::from(raw.pos));
+        if len > raw.values.len() || pos >= len {
+            return Err(format!(
+                "load choice {pos} of {len} is out of range for {} values",
+                raw.values.len()
+            ));
+        }
+
+        let mut values = raw.values;
+        values.truncate(len);
+        Ok(Load { values, pos })
+    }
+}
@@ -0,0 +1,424 @@
+//! Builds the happens-before graph of one loom execution and writes it out
+//! in Graphviz's DOT format.
+//!
+//! Every operation in the execution's [trace](crate::trace) becomes a node,
+//! grouped into a cluster per thread, with edges for:
+//!
+//! - program order, between consecutive operations of a thread;
+//! - reads-from, from the store an atomic load (or read-modify-write) read
+//!   to that load;
+//! - synchronizes-with, where a Release store is read by an Acquire load,
+//!   and from a spawn to the first operation of the new thread.
+//!
+//! A reads-from edge with no synchronizes-with edge next to it is the
+//! picture of a data race waiting to happen: the load saw the value, but
+//! nothing the storing thread did before it is guaranteed to be visible.
+//!
+//! The trace doesn't say which store a load read. The checkpoint does, for
+//! every load up to the last branch loom still had to explore, so those
+//! edges are exact. Loom runs any later load with its first choice, which
+//! is worked out here the way loom does it, from the newest store the thread
+//! could already see. Those edges are drawn dashed and labelled `rf?`.
+//! Fences, mutex unlocks and joins don't show up in the trace, so the
+//! synchronisation they add is missing from the inference and the graph.
+//!
+//! A compare-exchange that failed only reads, with its failure ordering.
+//! When the trace doesn't say whether a read-modify-write stored, as with
+//! loom's atomics used directly, it's taken to have, and every later load
+//! of that atomic is inferred: its stores can't be counted for sure any
+//! more, and the checkpoint's choices are positions among them.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::checkpoint::{Checkpoint, Load};
+use crate::trace::{Execution, Op};
+
+/// Loom keeps this many stores per atomic; a load picks one by its index
+/// in this ring.
+const HISTORY: usize = 7;
+
+/// The happens-before graph of an execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    /// The thread that performed the operation, or `None` for the initial
+    /// value of an atomic.
+    pub thread: Option<usize>,
+    pub label: String,
+    /// Whether the execution panicked right after this operation.
+    pub failed: bool,
+}
+
+/// An edge between two indices into [`Graph::nodes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    ProgramOrder,
+    /// `inferred` is set when the checkpoint didn't record the choice.
+    ReadsFrom {
+        inferred: bool,
+    },
+    SynchronizesWith,
+}
+
+impl Graph {
+    /// Builds the graph of `execution`. `checkpoint` should be the one
+    /// saved for the same execution; without it every reads-from edge is
+    /// inferred.
+    pub fn build(execution: &Execution, checkpoint: Option<&Checkpoint>) -> Self {
+        let loads = checkpoint.map_or_else(Vec::new, |checkpoint| checkpoint.loads().collect());
+        let mut builder = Builder {
+            graph: Graph {
+                nodes: Vec::new(),
+                edges: Vec::new(),
+            },
+            loads: loads.into_iter(),
+            clocks: HashMap::new(),
+            last: HashMap::new(),
+            spawned_by: HashMap::new(),
+            last_yield: HashMap::new(),
+            objects: BTreeMap::new(),
+            uncounted: HashSet::new(),
+        };
+
+        let failed = execution
+            .panic
+            .as_ref()
+            .and_then(|panic| panic.after.checked_sub(1));
+        for (index, event) in execution.events.iter().enumerate() {
+            builder.event(
+                event.thread,
+                &event.op,
+                event.to_string(),
+                failed == Some(index),
+            );
+        }
+        builder.graph
+    }
+
+    /// The graph in DOT format, e.g. for `dot -Tsvg`.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        dot.push_str("digraph execution {\n");
+        dot.push_str("  node [shape=box, fontname=\"monospace\"];\n");
+
+        let mut clusters: BTreeMap<Option<usize>, Vec<usize>> = BTreeMap::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            clusters.entry(node.thread).or_default().push(index);
+        }
+        for (thread, nodes) in clusters {
+            let (name, label) = match thread {
+                Some(thread) => (format!("thread_{thread}"), format!("thread {thread}")),
+                None => ("initial".to_string(), "initial values".to_string()),
+            };
+            writeln!(dot, "  subgraph cluster_{name} {{").unwrap();
+            writeln!(dot, "    label=\"{label}\";").unwrap();
+            if thread.is_none() {
+                dot.push_str("    style=dashed;\n");
+            }
+            for index in nodes {
+                let node = &self.nodes[index];
+                let label = escape(&node.label);
+                if node.failed {
+                    writeln!(
+                        dot,
+                        "    n{index} [label=\"{label}\\npanicked here\", color=red, fontcolor=red];"
+                    )
+                    .unwrap();
+                } else {
+                    writeln!(dot, "    n{index} [label=\"{label}\"];").unwrap();
+                }
+            }
+            dot.push_str("  }\n");
+        }
+
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::ProgramOrder => "label=\"po\", color=gray",
+                EdgeKind::ReadsFrom { inferred: false } => {
+                    "label=\"rf\", color=blue, fontcolor=blue, constraint=false"
+                }
+                EdgeKind::ReadsFrom { inferred: true } => {
+                    "label=\"rf?\", color=blue, fontcolor=blue, style=dashed, constraint=false"
+                }
+                EdgeKind::SynchronizesWith => {
+                    "label=\"sw\", color=darkgreen, fontcolor=darkgreen, penwidth=2"
+                }
+            };
+            writeln!(dot, "  n{} -> n{} [{style}];", edge.from, edge.to).unwrap();
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// A vector clock: how many operations of each thread happen before a
+/// point.
+#[derive(Debug, Clone, Default)]
+struct Clock(Vec<usize>);
+
+impl Clock {
+    fn get(&self, thread: usize) -> usize {
+        self.0.get(thread).copied().unwrap_or(0)
+    }
+
+    fn tick(&mut self, thread: usize) -> usize {
+        if self.0.len() <= thread {
+            self.0.resize(thread + 1, 0);
+        }
+        self.0[thread] += 1;
+        self.0[thread]
+    }
+
+    fn join(&mut self, other: &Clock) {
+        if self.0.len() < other.0.len() {
+            self.0.resize(other.0.len(), 0);
+        }
+        for (mine, theirs) in self.0.iter_mut().zip(&other.0) {
+            *mine = (*mine).max(*theirs);
+        }
+    }
+}
+
+/// A store to an atomic, in the order they happened.
+struct Store {
+    node: usize,
+    seq_cst: bool,
+    /// What an Acquire load reading this store synchronizes with.
+    release: Option<Clock>,
+    /// For each thread, the tick at which it first wrote or read this store.
+    seen: HashMap<usize, usize>,
+}
+
+/// Where an operation happened: its thread, that thread's clock tick, and
+/// its node.
+#[derive(Clone, Copy)]
+struct At {
+    thread: usize,
+    tick: usize,
+    node: usize,
+}
+
+struct Builder<'a> {
+    graph: Graph,
+    loads: std::vec::IntoIter<&'a Load>,
+    clocks: HashMap<usize, Clock>,
+    last: HashMap<usize, usize>,
+    /// The spawn node for threads that haven't done anything yet.
+    spawned_by: HashMap<usize, usize>,
+    last_yield: HashMap<usize, usize>,
+    /// Every store to each atomic, starting with its initial value.
+    objects: BTreeMap<String, Vec<Store>>,
+    /// Atomics that saw a read-modify-write which may not have stored.
+    uncounted: HashSet<String>,
+}
+
+impl Builder<'_> {
+    fn event(&mut self, thread: usize, op: &Op, label: String, failed: bool) {
+        let node = self.node(Some(thread), label, failed);
+        if let Some(prev) = self.last.insert(thread, node) {
+            self.edge(prev, node, EdgeKind::ProgramOrder);
+        }
+        if let Some(spawn) = self.spawned_by.remove(&thread) {
+            self.edge(spawn, node, EdgeKind::SynchronizesWith);
+        }
+        let tick = self.clocks.entry(thread).or_default().tick(thread);
+        let at = At { thread, tick, node };
+
+        match op {
+            Op::Spawn { thread: child } => {
+                let clock = self.clocks[&thread].clone();
+                self.clocks.insert(*child, clock);
+                self.spawned_by.insert(*child, node);
+            }
+            Op::Yield => {
+                self.last_yield.insert(thread, tick);
+            }
+            Op::Load { object, ordering } => {
+                let object = object.to_string();
+                if ordering == "unsync" {
+                    // Not a branch in the checkpoint; always the newest store
+                    let newest = self.stores(&object).len() - 1;
+                    self.read(at, &object, newest, ordering, false);
+                } else {
+                    let (store, inferred) = self.choose(thread, &object, ordering, false);
+                    self.read(at, &object, store, ordering, inferred);
+                }
+            }
+            Op::Store { object, ordering } => {
+                self.write(at, &object.to_string(), ordering, None);
+            }
+            Op::Rmw {
+                object,
+                ordering,
+                stored,
+            } => {
+                let object = object.to_string();
+                // Loom logs `success/failure`; an rmw that stored both reads
+                // and writes with the success ordering, and one that didn't
+                // only reads, with the failure ordering
+                let (success, failure) = ordering.split_once('/').unwrap_or((ordering, ordering));
+                let read = if *stored == Some(false) {
+                    failure
+                } else {
+                    success
+                };
+                let (store, inferred) = self.choose(thread, &object, read, true);
+                self.read(at, &object, store, read, inferred);
+                if *stored != Some(false) {
+                    // Continue the release sequence of the store it read
+                    let release = self.stores(&object)[store].release.clone();
+                    self.write(at, &object, success, release);
+                }
+                if stored.is_none() {
+                    self.uncounted.insert(object);
+                }
+            }
+            Op::Read { .. }
+            | Op::Write { .. }
+            | Op::Lock { .. }
+            | Op::Wait { .. }
+            | Op::Notify { .. }
+            | Op::Park
+            | Op::Done => {}
+        }
+    }
+
+    /// The stores of `object`, starting with its initial value.
+    fn stores(&mut self, object: &str) -> &mut Vec<Store> {
+        if !self.objects.contains_key(object) {
+            let node = self.node(None, format!("{object} initial value"), false);
+            self.objects.insert(
+                object.to_string(),
+                vec![Store {
+                    node,
+                    seq_cst: false,
+                    release: None,
+                    seen: HashMap::new(),
+                }],
+            );
+        }
+        self.objects.get_mut(object).unwrap()
+    }
+
+    /// The store a load reads, and whether it had to be inferred.
+    fn choose(&mut self, thread: usize, object: &str, ordering: &str, rmw: bool) -> (usize, bool) {
+        let count = self.stores(object).len();
+        // The oldest store still in loom's history
+        let oldest = count.saturating_sub(HISTORY);
+
+        // Every load takes its place in the checkpoint, but its choice only
+        // says which store it read while the stores are counted right
+        let load = self.loads.next();
+        if let Some(load) = load.filter(|_| !self.uncounted.contains(object)) {
+            let slot = usize::from(load.chosen());
+            if let Some(store) = (oldest..count).rev().find(|store| store % HISTORY == slot) {
+                return (store, false);
+            }
+        }
+
+        if rmw {
+            // A read-modify-write always reads the newest store
+            return (count - 1, true);
+        }
+
+        let clock = self.clocks[&thread].clone();
+        let last_yield = self.last_yield.get(&thread).copied();
+        let stores = &self.objects[object];
+        let seen = |store: &Store| {
+            store
+                .seen
+                .iter()
+                .any(|(&other, &tick)| tick <= clock.get(other))
+        };
+
+        // Loom offers every store not older than the newest one this thread
+        // can already see, and starts with the oldest of those. A store the
+        // thread saw before its last yield is skipped if there's a newer
+        // one, so spin loops make progress; a SeqCst load skips SeqCst
+        // stores that have a newer SeqCst store.
+        let newest_seen = (oldest..count)
+            .rev()
+            .find(|&store| store == 0 || seen(&stores[store]))
+            .unwrap_or(oldest);
+        let newest_seq_cst = (oldest..count).rev().find(|&store| stores[store].seq_cst);
+        let store = (newest_seen..count)
+            .find(|&store| {
+                let is_newest = store == count - 1;
+                let seen_before_yield = matches!(
+                    (stores[store].seen.get(&thread), last_yield),
+                    (Some(&first), Some(last_yield)) if first <= last_yield
+                );
+                let hidden_by_seq_cst =
+                    ordering == "SeqCst" && stores[store].seq_cst && newest_seq_cst != Some(store);
+                is_newest || !(seen_before_yield || hidden_by_seq_cst)
+            })
+            .unwrap_or(count - 1);
+        (store, true)
+    }
+
+    fn read(&mut self, at: At, object: &str, store: usize, ordering: &str, inferred: bool) {
+        let store = &mut self.stores(object)[store];
+        store.seen.entry(at.thread).or_insert(at.tick);
+        let (from, release) = (store.node, store.release.clone());
+
+        self.edge(from, at.node, EdgeKind::ReadsFrom { inferred });
+        if let Some(release) = release.filter(|_| is_acquire(ordering)) {
+            self.edge(from, at.node, EdgeKind::SynchronizesWith);
+            self.clocks.get_mut(&at.thread).unwrap().join(&release);
+        }
+    }
+
+    fn write(&mut self, at: At, object: &str, ordering: &str, mut release: Option<Clock>) {
+        if is_release(ordering) {
+            release
+                .get_or_insert_with(Clock::default)
+                .join(&self.clocks[&at.thread]);
+        }
+        let store = Store {
+            node: at.node,
+            seq_cst: ordering == "SeqCst",
+            release,
+            seen: HashMap::from([(at.thread, at.tick)]),
+        };
+        self.stores(object).push(store);
+    }
+
+    fn node(&mut self, thread: Option<usize>, label: String, failed: bool) -> usize {
+        self.graph.nodes.push(Node {
+            thread,
+            label,
+            failed,
+        });
+        self.graph.nodes.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, to: usize, kind: EdgeKind) {
+        self.graph.edges.push(Edge { from, to, kind });
+    }
+}
+
+fn is_acquire(ordering: &str) -> bool {
+    matches!(ordering, "Acquire" | "AcqRel" | "SeqCst")
+}
+
+fn is_release(ordering: &str) -> bool {
+    matches!(ordering, "Release" | "AcqRel" | "SeqCst")
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
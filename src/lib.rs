@@ -1,18 +1,71 @@
+pub mod artifacts;
 pub mod channels;
+pub mod checkpoint;
+pub mod happens_before;
+pub mod linearizability;
+pub mod litmus;
 pub mod locks;
+pub mod model;
+pub mod outcomes;
+pub mod rng;
+pub mod sequential;
+pub mod stats;
 pub mod sync;
+pub mod trace;
+pub mod viewer;
 
+/// `#[concurrent_test]`, the attribute form of [`concurrent_test!`].
+///
+/// It lives in its own module because attributes and `macro_rules!` macros
+/// share a namespace, so both can't be exported as `concurrent_test`.
+pub mod attr {
+    pub use rust_atomics_macros::concurrent_test;
+}
+
+/// Runs the body directly, inside the loom model checker when built with
+/// `--cfg loom`, or under shuttle's randomized scheduler with `--cfg shuttle`.
+///
+/// Options for [`model::Builder`] can be given before the body, either as
+/// `name = value` or as a bare flag, e.g.
+/// `concurrent_test!(preemption_bound = 3, max_branches = 10_000, { ... })` or
+/// `concurrent_test!(should_find_bug, { ... })`.
+///
+/// In std mode the body runs on the calling thread and may borrow its
+/// locals; under loom and shuttle it must be `Send + Sync + 'static`. Either
+/// way it evaluates to `()`.
 #[macro_export]
 macro_rules! concurrent_test {
     ($body:expr) => {
-        #[cfg(not(loom))]
-        {
-            $body
-        }
+        $crate::model::Builder::new().check(|| $body)
+    };
+
+    ($($options:tt)+) => {
+        $crate::__concurrent_test_options!(($crate::model::Builder::new()) $($options)+)
+    };
+}
 
-        #[cfg(loom)]
-        {
-            loom::model(|| $body)
-        }
+/// Applies `concurrent_test!` options one at a time, then runs the body.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __concurrent_test_options {
+    (($builder:expr) $body:block) => {
+        $builder.check(|| $body)
+    };
+
+    (($builder:expr) $flag:ident , $($rest:tt)+) => {
+        $crate::__concurrent_test_options!(($builder.$flag()) $($rest)+)
+    };
+
+    (($builder:expr) $option:ident = $value:expr , $($rest:tt)+) => {
+        $crate::__concurrent_test_options!(($builder.$option($value)) $($rest)+)
+    };
+}
+
+/// Builds a `BTreeSet` from a list of values, for comparing against the
+/// result of [`outcomes::explore_outcomes`].
+#[macro_export]
+macro_rules! set {
+    ($($value:expr),* $(,)?) => {
+        ::std::collections::BTreeSet::from([$($value),*])
     };
 }
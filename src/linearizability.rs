@@ -0,0 +1,308 @@
+//! Checks that a concurrent object behaves like a sequential one.
+//!
+//! A [`History`] records when each operation on a shared object was invoked
+//! and when it returned, from every thread. [`History::check`] then looks for
+//! a linearization: one order of the operations that respects real time (an
+//! operation that returned before another was invoked comes first) and in
+//! which a sequential [`Spec`] of the object returns what every operation
+//! actually returned. The search is Wing and Gong's, with Lowe's memoization
+//! of the (linearized operations, spec state) pairs already ruled out, as in
+//! Porcupine.
+//!
+//! Recording inside `concurrent_test!` checks every execution loom explores:
+//!
+//! ```ignore
+//! concurrent_test!({
+//!     let channel = Arc::new(SafeChannel::new());
+//!     let history = History::new();
+//!     // each thread wraps its calls in `history.call(...)`
+//!     history.assert_linearizable(Queue::default());
+//! });
+//! ```
+//!
+//! The history is kept behind a real mutex, not `crate::sync::Mutex`, so
+//! recording adds no branches to the model. On real threads that mutex does
+//! synchronize the recording threads a little, which may hide some bugs.
+
+use std::collections::HashSet;
+use std::fmt::{self, Debug, Display};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+/// The sequential specification of an object: its state, and what each
+/// operation does to it.
+///
+/// The state is hashed to remember which states the search has ruled out, so
+/// it should be small, e.g. a `VecDeque` for a channel or a plain value for
+/// a lock.
+pub trait Spec: Clone + Eq + Hash + Debug {
+    type Op: Clone + Debug + Send;
+    type Ret: Clone + Debug + PartialEq + Send;
+
+    /// Applies `op` to the state and returns what it returns.
+    fn apply(&mut self, op: &Self::Op) -> Self::Ret;
+
+    /// Whether `op` would wait in this state, such as a receive on an empty
+    /// channel, so it can't take effect here.
+    fn blocks(&self, op: &Self::Op) -> bool {
+        let _ = op;
+        false
+    }
+}
+
+/// The operations on a shared object, as invoked and returned by every
+/// thread. Clones record into the same history.
+pub struct History<S: Spec> {
+    events: Arc<Mutex<Events<S>>>,
+}
+
+struct Events<S: Spec> {
+    operations: Vec<Operation<S>>,
+    /// The next event's position in the history.
+    clock: usize,
+}
+
+/// One operation in a [`History`].
+#[derive(Debug, Clone)]
+pub struct Operation<S: Spec> {
+    /// The calling thread, as its `Debug` form.
+    pub thread: String,
+    pub op: S::Op,
+    /// What it returned, or `None` if it hasn't returned yet.
+    pub ret: Option<S::Ret>,
+    /// The position of its invocation in the history.
+    pub invoked: usize,
+    /// The position of its response in the history, if it has returned.
+    pub returned: Option<usize>,
+}
+
+/// An invoked operation, to be passed to [`History::respond`] when it
+/// returns.
+#[derive(Debug)]
+#[must_use = "an operation that never responds is treated as still running"]
+pub struct Call {
+    index: usize,
+}
+
+/// A history with no linearization.
+#[derive(Debug, Clone)]
+pub struct NotLinearizable<S: Spec> {
+    pub operations: Vec<Operation<S>>,
+    /// The longest order of operations the spec accepted, as indices into
+    /// `operations`.
+    pub longest: Vec<usize>,
+    /// The spec's state after `longest`.
+    pub state: S,
+}
+
+impl<S: Spec> History<S> {
+    pub fn new() -> Self {
+        Self {
+            events: Arc::new(Mutex::new(Events {
+                operations: Vec::new(),
+                clock: 0,
+            })),
+        }
+    }
+
+    /// Records that the current thread invoked `op`.
+    ///
+    /// Under loom and shuttle this has to run inside the model, as it asks
+    /// the model which thread is running.
+    pub fn invoke(&self, op: S::Op) -> Call {
+        let thread = format!("{:?}", crate::sync::thread::current().id());
+        let mut events = self.events.lock().unwrap();
+        let invoked = events.tick();
+        events.operations.push(Operation {
+            thread,
+            op,
+            ret: None,
+            invoked,
+            returned: None,
+        });
+        Call {
+            index: events.operations.len() - 1,
+        }
+    }
+
+    /// Records that `call` returned `ret`.
+    pub fn respond(&self, call: Call, ret: S::Ret) {
+        let mut events = self.events.lock().unwrap();
+        let returned = events.tick();
+        let operation = &mut events.operations[call.index];
+        operation.ret = Some(ret);
+        operation.returned = Some(returned);
+    }
+
+    /// Runs `f` as the operation `op`, recording its invocation and its
+    /// response.
+    pub fn call(&self, op: S::Op, f: impl FnOnce() -> S::Ret) -> S::Ret {
+        let call = self.invoke(op);
+        let ret = f();
+        self.respond(call, ret.clone());
+        ret
+    }
+
+    /// Every operation recorded so far, in the order they were invoked.
+    pub fn operations(&self) -> Vec<Operation<S>> {
+        self.events.lock().unwrap().operations.clone()
+    }
+
+    /// Looks for a linearization of the history, starting from `spec`.
+    ///
+    /// Returns the order found, as indices into
+    /// [`operations`](Self::operations). Operations that haven't returned may
+    /// take effect at any point after they were invoked, or not at all, so
+    /// they only appear in it if they had to.
+    pub fn check(&self, spec: S) -> Result<Vec<usize>, NotLinearizable<S>> {
+        check(&self.operations(), spec)
+    }
+
+    /// Panics with the history if it isn't linearizable.
+    #[track_caller]
+    pub fn assert_linearizable(&self, spec: S) {
+        if let Err(error) = self.check(spec) {
+            panic!("{error}");
+        }
+    }
+}
+
+impl<S: Spec> Clone for History<S> {
+    fn clone(&self) -> Self {
+        Self {
+            events: self.events.clone(),
+        }
+    }
+}
+
+impl<S: Spec> Default for History<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Spec> Debug for History<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.operations()).finish()
+    }
+}
+
+impl<S: Spec> Events<S> {
+    fn tick(&mut self) -> usize {
+        self.clock += 1;
+        self.clock - 1
+    }
+}
+
+/// Looks for a linearization of `operations`, starting from `spec`. See
+/// [`History::check`].
+pub fn check<S: Spec>(
+    operations: &[Operation<S>],
+    spec: S,
+) -> Result<Vec<usize>, NotLinearizable<S>> {
+    let mut search = Search {
+        operations,
+        linearized: vec![false; operations.len()],
+        order: Vec::new(),
+        ruled_out: HashSet::new(),
+        longest: (Vec::new(), spec.clone()),
+    };
+    if search.extend(spec) {
+        return Ok(search.order);
+    }
+
+    let (longest, state) = search.longest;
+    Err(NotLinearizable {
+        operations: operations.to_vec(),
+        longest,
+        state,
+    })
+}
+
+/// A depth-first search for a linearization, one operation at a time.
+struct Search<'a, S: Spec> {
+    operations: &'a [Operation<S>],
+    linearized: Vec<bool>,
+    order: Vec<usize>,
+    /// The points the search has already failed to extend.
+    ruled_out: HashSet<(Vec<bool>, S)>,
+    longest: (Vec<usize>, S),
+}
+
+impl<S: Spec> Search<'_, S> {
+    /// Whether `order` can be extended to a linearization from `state`.
+    fn extend(&mut self, state: S) -> bool {
+        if self.order.len() > self.longest.0.len() {
+            self.longest = (self.order.clone(), state.clone());
+        }
+
+        // Every operation that returned before the earliest response still
+        // outstanding has to take effect first
+        let unlinearized = || (0..self.operations.len()).filter(|&i| !self.linearized[i]);
+        let Some(deadline) = unlinearized()
+            .filter_map(|i| self.operations[i].returned)
+            .min()
+        else {
+            // Only operations that never returned are left, and they may
+            // never have taken effect
+            return true;
+        };
+        if !self
+            .ruled_out
+            .insert((self.linearized.clone(), state.clone()))
+        {
+            return false;
+        }
+
+        let candidates: Vec<_> = unlinearized()
+            .filter(|&i| self.operations[i].invoked < deadline)
+            .collect();
+        for i in candidates {
+            let operation = &self.operations[i];
+            if state.blocks(&operation.op) {
+                continue;
+            }
+            let mut next = state.clone();
+            let ret = next.apply(&operation.op);
+            if operation.ret.as_ref().is_some_and(|actual| *actual != ret) {
+                continue;
+            }
+
+            self.linearized[i] = true;
+            self.order.push(i);
+            if self.extend(next) {
+                return true;
+            }
+            self.order.pop();
+            self.linearized[i] = false;
+        }
+        false
+    }
+}
+
+impl<S: Spec> Display for Operation<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {:?}", self.thread, self.op)?;
+        match (&self.ret, self.returned) {
+            (Some(ret), Some(returned)) => write!(f, " -> {ret:?} [{}, {returned}]", self.invoked),
+            _ => write!(f, " (never returned) [{}, ..]", self.invoked),
+        }
+    }
+}
+
+impl<S: Spec> Display for NotLinearizable<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "history is not linearizable:")?;
+        for (i, operation) in self.operations.iter().enumerate() {
+            writeln!(f, "  #{i} {operation}")?;
+        }
+
+        let longest: Vec<_> = self.longest.iter().map(|i| format!("#{i}")).collect();
+        write!(
+            f,
+            "the longest order the spec accepts is [{}], after which its state is {:?}",
+            longest.join(", "),
+            self.state
+        )
+    }
+}
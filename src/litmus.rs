@@ -0,0 +1,582 @@
+//! The classic memory-model litmus tests.
+//!
+//! Each shape is parameterised over the `Ordering` of every access and knows
+//! which outcomes the C++11 memory model allows for that combination. Running
+//! one explores it with [`explore_outcomes`] against `crate::sync::atomic` and
+//! returns a [`Report`] comparing what was observed with what is allowed.
+//!
+//! Loom doesn't implement the full C++11 model, so a report can legitimately
+//! disagree with it under loom: loads never read from stores that haven't
+//! executed yet (so load buffering is never seen), and SeqCst loads and
+//! stores are treated like AcqRel (only SeqCst fences are fully modelled).
+//! The report says exactly where the two differ.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Debug, Display};
+
+use crate::model::Builder;
+use crate::outcomes::assert_outcomes;
+use crate::sync::atomic::AtomicU32;
+use crate::sync::atomic::Ordering::{self, AcqRel, Acquire, Relaxed, Release, SeqCst};
+use crate::sync::{Arc, thread};
+
+/// A litmus test: a small concurrent program and the outcomes it may have.
+pub trait Litmus: Debug + Copy + Send + Sync + 'static {
+    type Outcome: Ord + Debug + Send + 'static;
+
+    /// Every outcome the C++11 memory model allows for these orderings.
+    fn allowed(&self) -> BTreeSet<Self::Outcome>;
+
+    /// Runs the program once and returns what it observed.
+    fn execute(&self) -> Self::Outcome;
+
+    /// Explores the program with default options.
+    fn run(&self) -> Report<Self::Outcome> {
+        self.run_with(&Builder::new())
+    }
+
+    /// Explores the program with the given options.
+    fn run_with(&self, builder: &Builder) -> Report<Self::Outcome> {
+        let test = *self;
+        Report {
+            test: format!("{self:?}"),
+            observed: builder.explore_outcomes(move || test.execute()),
+            allowed: self.allowed(),
+        }
+    }
+}
+
+/// The outcomes of a litmus test run, next to the ones the model allows.
+#[derive(Debug, Clone)]
+pub struct Report<O> {
+    pub test: String,
+    pub observed: BTreeSet<O>,
+    pub allowed: BTreeSet<O>,
+}
+
+impl<O: Ord + Debug> Report<O> {
+    /// Outcomes that were observed but the model forbids.
+    pub fn forbidden(&self) -> Vec<&O> {
+        self.observed.difference(&self.allowed).collect()
+    }
+
+    /// Outcomes the model allows that were never observed.
+    pub fn missing(&self) -> Vec<&O> {
+        self.allowed.difference(&self.observed).collect()
+    }
+
+    /// Whether exactly the allowed outcomes were observed.
+    pub fn matches(&self) -> bool {
+        self.observed == self.allowed
+    }
+
+    /// Asserts the observed outcomes match, as [`assert_outcomes`] does.
+    #[track_caller]
+    pub fn assert_matches(&self) {
+        assert_outcomes(&self.observed, &self.allowed);
+    }
+}
+
+impl<O: Ord + Debug> Display for Report<O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.test)?;
+        writeln!(f, "  observed: {:?}", self.observed)?;
+        writeln!(f, "  allowed:  {:?}", self.allowed)?;
+        if self.matches() {
+            write!(f, "  matches the memory model")
+        } else {
+            writeln!(f, "  forbidden but observed: {:?}", self.forbidden())?;
+            write!(f, "  allowed but missing:    {:?}", self.missing())
+        }
+    }
+}
+
+/// The strongest valid store ordering implied by `order`.
+fn for_store(order: Ordering) -> Ordering {
+    match order {
+        Release | AcqRel => Release,
+        SeqCst => SeqCst,
+        _ => Relaxed,
+    }
+}
+
+/// The strongest valid load ordering implied by `order`.
+fn for_load(order: Ordering) -> Ordering {
+    match order {
+        Acquire | AcqRel => Acquire,
+        SeqCst => SeqCst,
+        _ => Relaxed,
+    }
+}
+
+fn is_release(order: Ordering) -> bool {
+    matches!(order, Release | AcqRel | SeqCst)
+}
+
+fn is_acquire(order: Ordering) -> bool {
+    matches!(order, Acquire | AcqRel | SeqCst)
+}
+
+/// Every combination of `values` across `N` positions.
+fn all<const N: usize>(values: &[u32]) -> BTreeSet<[u32; N]> {
+    let mut outcomes = BTreeSet::from([[0; N]]);
+    for i in 0..N {
+        outcomes = outcomes
+            .into_iter()
+            .flat_map(|outcome| {
+                values.iter().map(move |&value| {
+                    let mut outcome = outcome;
+                    outcome[i] = value;
+                    outcome
+                })
+            })
+            .collect();
+    }
+    outcomes
+}
+
+fn shared() -> (Arc<AtomicU32>, Arc<AtomicU32>) {
+    (Arc::new(AtomicU32::new(0)), Arc::new(AtomicU32::new(0)))
+}
+
+/// MP, message passing:
+///
+/// ```text
+/// T1: data = 1; flag = 1    T2: r1 = flag; r2 = data
+/// ```
+///
+/// `(1, 0)` is forbidden only if the flag store releases and the flag load
+/// acquires.
+#[derive(Debug, Clone, Copy)]
+pub struct MessagePassing {
+    pub store_data: Ordering,
+    pub store_flag: Ordering,
+    pub load_flag: Ordering,
+    pub load_data: Ordering,
+}
+
+impl MessagePassing {
+    /// Every access uses `order`, weakened to what the access allows.
+    pub fn uniform(order: Ordering) -> Self {
+        Self {
+            store_data: for_store(order),
+            store_flag: for_store(order),
+            load_flag: for_load(order),
+            load_data: for_load(order),
+        }
+    }
+}
+
+impl Litmus for MessagePassing {
+    type Outcome = (u32, u32);
+
+    fn allowed(&self) -> BTreeSet<(u32, u32)> {
+        let mut allowed = BTreeSet::from([(0, 0), (0, 1), (1, 0), (1, 1)]);
+        if is_release(self.store_flag) && is_acquire(self.load_flag) {
+            allowed.remove(&(1, 0));
+        }
+        allowed
+    }
+
+    fn execute(&self) -> (u32, u32) {
+        let (data, flag) = shared();
+        let t = *self;
+
+        let writer = thread::spawn({
+            let (data, flag) = (data.clone(), flag.clone());
+            move || {
+                data.store(1, t.store_data);
+                flag.store(1, t.store_flag);
+            }
+        });
+
+        let r1 = flag.load(t.load_flag);
+        let r2 = data.load(t.load_data);
+        writer.join().unwrap();
+
+        (r1, r2)
+    }
+}
+
+/// SB, store buffering:
+///
+/// ```text
+/// T1: x = 1; r1 = y    T2: y = 1; r2 = x
+/// ```
+///
+/// `(0, 0)` is forbidden only if all four accesses are SeqCst.
+#[derive(Debug, Clone, Copy)]
+pub struct StoreBuffering {
+    pub store_x: Ordering,
+    pub load_y: Ordering,
+    pub store_y: Ordering,
+    pub load_x: Ordering,
+}
+
+impl StoreBuffering {
+    /// Every access uses `order`, weakened to what the access allows.
+    pub fn uniform(order: Ordering) -> Self {
+        Self {
+            store_x: for_store(order),
+            load_y: for_load(order),
+            store_y: for_store(order),
+            load_x: for_load(order),
+        }
+    }
+}
+
+impl Litmus for StoreBuffering {
+    type Outcome = (u32, u32);
+
+    fn allowed(&self) -> BTreeSet<(u32, u32)> {
+        let mut allowed = BTreeSet::from([(0, 0), (0, 1), (1, 0), (1, 1)]);
+        if [self.store_x, self.load_y, self.store_y, self.load_x] == [SeqCst; 4] {
+            allowed.remove(&(0, 0));
+        }
+        allowed
+    }
+
+    fn execute(&self) -> (u32, u32) {
+        let (x, y) = shared();
+        let t = *self;
+
+        let other = thread::spawn({
+            let (x, y) = (x.clone(), y.clone());
+            move || {
+                x.store(1, t.store_x);
+                y.load(t.load_y)
+            }
+        });
+
+        y.store(1, t.store_y);
+        let r2 = x.load(t.load_x);
+        let r1 = other.join().unwrap();
+
+        (r1, r2)
+    }
+}
+
+/// LB, load buffering:
+///
+/// ```text
+/// T1: r1 = x; y = 1    T2: r2 = y; x = 1
+/// ```
+///
+/// `(1, 1)` is forbidden if either store synchronizes with the load that
+/// reads it, since each load would then read from a store that happens after
+/// it.
+#[derive(Debug, Clone, Copy)]
+pub struct LoadBuffering {
+    pub load_x: Ordering,
+    pub store_y: Ordering,
+    pub load_y: Ordering,
+    pub store_x: Ordering,
+}
+
+impl LoadBuffering {
+    /// Every access uses `order`, weakened to what the access allows.
+    pub fn uniform(order: Ordering) -> Self {
+        Self {
+            load_x: for_load(order),
+            store_y: for_store(order),
+            load_y: for_load(order),
+            store_x: for_store(order),
+        }
+    }
+}
+
+impl Litmus for LoadBuffering {
+    type Outcome = (u32, u32);
+
+    fn allowed(&self) -> BTreeSet<(u32, u32)> {
+        let mut allowed = BTreeSet::from([(0, 0), (0, 1), (1, 0), (1, 1)]);
+        if (is_release(self.store_y) && is_acquire(self.load_y))
+            || (is_release(self.store_x) && is_acquire(self.load_x))
+        {
+            allowed.remove(&(1, 1));
+        }
+        allowed
+    }
+
+    fn execute(&self) -> (u32, u32) {
+        let (x, y) = shared();
+        let t = *self;
+
+        let other = thread::spawn({
+            let (x, y) = (x.clone(), y.clone());
+            move || {
+                let r1 = x.load(t.load_x);
+                y.store(1, t.store_y);
+                r1
+            }
+        });
+
+        let r2 = y.load(t.load_y);
+        x.store(1, t.store_x);
+        let r1 = other.join().unwrap();
+
+        (r1, r2)
+    }
+}
+
+/// IRIW, independent reads of independent writes:
+///
+/// ```text
+/// T1: x = 1    T2: y = 1    T3: r1 = x; r2 = y    T4: r3 = y; r4 = x
+/// ```
+///
+/// The readers disagreeing on the order of the writes, `[1, 0, 1, 0]`, is
+/// forbidden only if every access is SeqCst.
+#[derive(Debug, Clone, Copy)]
+pub struct IndependentReads {
+    pub store_x: Ordering,
+    pub store_y: Ordering,
+    pub load_x_first: Ordering,
+    pub load_y_second: Ordering,
+    pub load_y_first: Ordering,
+    pub load_x_second: Ordering,
+}
+
+impl IndependentReads {
+    /// Every access uses `order`, weakened to what the access allows.
+    pub fn uniform(order: Ordering) -> Self {
+        Self {
+            store_x: for_store(order),
+            store_y: for_store(order),
+            load_x_first: for_load(order),
+            load_y_second: for_load(order),
+            load_y_first: for_load(order),
+            load_x_second: for_load(order),
+        }
+    }
+
+    fn orderings(&self) -> [Ordering; 6] {
+        [
+            self.store_x,
+            self.store_y,
+            self.load_x_first,
+            self.load_y_second,
+            self.load_y_first,
+            self.load_x_second,
+        ]
+    }
+}
+
+impl Litmus for IndependentReads {
+    type Outcome = [u32; 4];
+
+    fn allowed(&self) -> BTreeSet<[u32; 4]> {
+        let mut allowed = all(&[0, 1]);
+        if self.orderings() == [SeqCst; 6] {
+            allowed.remove(&[1, 0, 1, 0]);
+        }
+        allowed
+    }
+
+    fn execute(&self) -> [u32; 4] {
+        let (x, y) = shared();
+        let t = *self;
+
+        let writer_x = thread::spawn({
+            let x = x.clone();
+            move || x.store(1, t.store_x)
+        });
+        let writer_y = thread::spawn({
+            let y = y.clone();
+            move || y.store(1, t.store_y)
+        });
+        let reader = thread::spawn({
+            let (x, y) = (x.clone(), y.clone());
+            move || (x.load(t.load_x_first), y.load(t.load_y_second))
+        });
+
+        // The main thread is the second reader, which keeps this within
+        // loom's thread limit
+        let r3 = y.load(t.load_y_first);
+        let r4 = x.load(t.load_x_second);
+        let (r1, r2) = reader.join().unwrap();
+        writer_x.join().unwrap();
+        writer_y.join().unwrap();
+
+        [r1, r2, r3, r4]
+    }
+}
+
+/// 2+2W, two threads each writing two variables in opposite orders:
+///
+/// ```text
+/// T1: x = 1; y = 2    T2: y = 1; x = 2
+/// ```
+///
+/// The outcome is the final `(x, y)`. `(1, 1)` means each thread's first
+/// write won, which is forbidden only if every store is SeqCst.
+#[derive(Debug, Clone, Copy)]
+pub struct TwoPlusTwoWrites {
+    pub store_x1: Ordering,
+    pub store_y2: Ordering,
+    pub store_y1: Ordering,
+    pub store_x2: Ordering,
+}
+
+impl TwoPlusTwoWrites {
+    /// Every access uses `order`, weakened to what the access allows.
+    pub fn uniform(order: Ordering) -> Self {
+        Self {
+            store_x1: for_store(order),
+            store_y2: for_store(order),
+            store_y1: for_store(order),
+            store_x2: for_store(order),
+        }
+    }
+}
+
+impl Litmus for TwoPlusTwoWrites {
+    type Outcome = (u32, u32);
+
+    fn allowed(&self) -> BTreeSet<(u32, u32)> {
+        let mut allowed = BTreeSet::from([(1, 1), (1, 2), (2, 1), (2, 2)]);
+        if [self.store_x1, self.store_y2, self.store_y1, self.store_x2] == [SeqCst; 4] {
+            allowed.remove(&(1, 1));
+        }
+        allowed
+    }
+
+    fn execute(&self) -> (u32, u32) {
+        let (x, y) = shared();
+        let t = *self;
+
+        let other = thread::spawn({
+            let (x, y) = (x.clone(), y.clone());
+            move || {
+                x.store(1, t.store_x1);
+                y.store(2, t.store_y2);
+            }
+        });
+
+        y.store(1, t.store_y1);
+        x.store(2, t.store_x2);
+        other.join().unwrap();
+
+        // Both stores to each variable happen before these loads, so they
+        // read the last store in modification order
+        (x.load(Relaxed), y.load(Relaxed))
+    }
+}
+
+/// WRC, write-to-read causality:
+///
+/// ```text
+/// T1: x = 1    T2: r1 = x; y = 1    T3: r2 = y; r3 = x
+/// ```
+///
+/// `(1, 1, 0)` is forbidden if the store to `y` synchronizes with the load of
+/// it: T2's read of `x` then happens before T3's, and read-read coherence
+/// stops T3 from seeing an older value. The orderings on `x` don't matter.
+#[derive(Debug, Clone, Copy)]
+pub struct WriteReadCausality {
+    pub store_x: Ordering,
+    pub load_x_relay: Ordering,
+    pub store_y: Ordering,
+    pub load_y: Ordering,
+    pub load_x: Ordering,
+}
+
+impl WriteReadCausality {
+    /// Every access uses `order`, weakened to what the access allows.
+    pub fn uniform(order: Ordering) -> Self {
+        Self {
+            store_x: for_store(order),
+            load_x_relay: for_load(order),
+            store_y: for_store(order),
+            load_y: for_load(order),
+            load_x: for_load(order),
+        }
+    }
+}
+
+impl Litmus for WriteReadCausality {
+    type Outcome = [u32; 3];
+
+    fn allowed(&self) -> BTreeSet<[u32; 3]> {
+        let mut allowed = all(&[0, 1]);
+        if is_release(self.store_y) && is_acquire(self.load_y) {
+            allowed.remove(&[1, 1, 0]);
+        }
+        allowed
+    }
+
+    fn execute(&self) -> [u32; 3] {
+        let (x, y) = shared();
+        let t = *self;
+
+        let writer = thread::spawn({
+            let x = x.clone();
+            move || x.store(1, t.store_x)
+        });
+        let relay = thread::spawn({
+            let (x, y) = (x.clone(), y.clone());
+            move || {
+                let r1 = x.load(t.load_x_relay);
+                y.store(1, t.store_y);
+                r1
+            }
+        });
+
+        let r2 = y.load(t.load_y);
+        let r3 = x.load(t.load_x);
+        let r1 = relay.join().unwrap();
+        writer.join().unwrap();
+
+        [r1, r2, r3]
+    }
+}
+
+/// CoRR, read-read coherence:
+///
+/// ```text
+/// T1: x = 1    T2: r1 = x; r2 = x
+/// ```
+///
+/// `(1, 0)` is forbidden for every ordering: two reads of one location in
+/// the same thread can't go backwards in its modification order.
+#[derive(Debug, Clone, Copy)]
+pub struct ReadReadCoherence {
+    pub store: Ordering,
+    pub load_first: Ordering,
+    pub load_second: Ordering,
+}
+
+impl ReadReadCoherence {
+    /// Every access uses `order`, weakened to what the access allows.
+    pub fn uniform(order: Ordering) -> Self {
+        Self {
+            store: for_store(order),
+            load_first: for_load(order),
+            load_second: for_load(order),
+        }
+    }
+}
+
+impl Litmus for ReadReadCoherence {
+    type Outcome = (u32, u32);
+
+    fn allowed(&self) -> BTreeSet<(u32, u32)> {
+        BTreeSet::from([(0, 0), (0, 1), (1, 1)])
+    }
+
+    fn execute(&self) -> (u32, u32) {
+        let x = Arc::new(AtomicU32::new(0));
+        let t = *self;
+
+        let writer = thread::spawn({
+            let x = x.clone();
+            move || x.store(1, t.store)
+        });
+
+        let r1 = x.load(t.load_first);
+        let r2 = x.load(t.load_second);
+        writer.join().unwrap();
+
+        (r1, r2)
+    }
+}
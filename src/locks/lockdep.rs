@@ -0,0 +1,244 @@
+//! Lock-order checking for the crate's locks, with the `lockdep` feature.
+//!
+//! Every lock gets an id, and every thread keeps the list of locks it
+//! holds. Taking a lock while holding others records an edge from each of
+//! them to the new one, and if the new one can already reach one of them,
+//! the two orders can deadlock against each other: some run where the two
+//! threads each take their first lock before the other's second would hang.
+//! That's reported with a panic right away, even though this run didn't
+//! hang, with a backtrace of every acquisition that made up the cycle.
+//!
+//! Locks are told apart by instance rather than by where they were created,
+//! and a read of an [`RwLock`](super::RwLock) counts the same as a write, as
+//! a waiting writer stops new readers. Taking a lock the thread already
+//! holds is reported too.
+
+use std::backtrace::Backtrace;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed};
+use std::sync::{LazyLock, Mutex, MutexGuard, PoisonError};
+
+/// Identifies a lock to the checker, and forgets its edges when dropped.
+pub(crate) struct LockId {
+    /// Handed out on first use, so locks can still be made in a `const`;
+    /// `0` until then.
+    id: AtomicUsize,
+    kind: &'static str,
+}
+
+/// A thread, as a model thread on the OS thread running it, as loom and
+/// shuttle run all of a test's threads on one.
+type Thread = (std::thread::ThreadId, crate::sync::thread::ThreadId);
+
+/// The thread that took a lock, kept by its guard so the lock stops
+/// counting as held by that thread wherever the guard is dropped.
+#[derive(Clone, Copy)]
+pub(crate) struct Held(Thread);
+
+/// The second of two locks was taken while holding the first.
+struct Edge {
+    thread: String,
+    backtrace: Backtrace,
+}
+
+#[derive(Default)]
+struct State {
+    /// The locks each thread holds, in the order it took them.
+    held: HashMap<Thread, Vec<usize>>,
+    /// From a lock to the locks taken while holding it.
+    edges: HashMap<usize, HashMap<usize, Edge>>,
+    kinds: HashMap<usize, &'static str>,
+}
+
+impl State {
+    /// The edges of a path from `from` to `to`, if there is one.
+    fn path(&self, from: usize, to: usize) -> Option<Vec<(usize, usize)>> {
+        let mut stack = vec![(from, Vec::new())];
+        let mut seen = vec![from];
+        while let Some((lock, path)) = stack.pop() {
+            for &next in self
+                .edges
+                .get(&lock)
+                .into_iter()
+                .flat_map(|edges| edges.keys())
+            {
+                let mut path = path.clone();
+                path.push((lock, next));
+                if next == to {
+                    return Some(path);
+                }
+                if !seen.contains(&next) {
+                    seen.push(next);
+                    stack.push((next, path));
+                }
+            }
+        }
+        None
+    }
+
+    fn name(&self, lock: usize) -> String {
+        format!("{} #{lock}", self.kinds.get(&lock).unwrap_or(&"lock"))
+    }
+}
+
+static STATE: LazyLock<Mutex<State>> = LazyLock::new(Default::default);
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+fn state() -> MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn current() -> Thread {
+    (
+        std::thread::current().id(),
+        crate::sync::thread::current().id(),
+    )
+}
+
+impl LockId {
+    /// A new lock, named `kind` in reports.
+    pub(crate) const fn new(kind: &'static str) -> LockId {
+        LockId {
+            id: AtomicUsize::new(0),
+            kind,
+        }
+    }
+
+    fn id(&self) -> usize {
+        let id = self.id.load(Acquire);
+        if id != 0 {
+            return id;
+        }
+        let new = NEXT_ID.fetch_add(1, Relaxed);
+        match self.id.compare_exchange(0, new, AcqRel, Acquire) {
+            Ok(_) => new,
+            Err(id) => id,
+        }
+    }
+
+    /// Checks taking this lock against the order locks were taken in so
+    /// far, and counts it as held by this thread. Called before waiting for
+    /// the lock, so a deadlock is reported rather than hung on.
+    ///
+    /// # Panics
+    ///
+    /// If the thread holds the lock already, or some thread took a lock
+    /// this thread holds while (indirectly) holding this one.
+    pub(crate) fn acquire(&self) -> Held {
+        let id = self.id();
+        let thread = current();
+        let mut state = state();
+        state.kinds.insert(id, self.kind);
+        let held = state.held.get(&thread).cloned().unwrap_or_default();
+
+        if held.contains(&id) {
+            let message = format!(
+                "{:?} is taking {}, which it already holds, at:\n{}",
+                thread.1,
+                state.name(id),
+                Backtrace::force_capture()
+            );
+            drop(state);
+            panic!("{message}");
+        }
+
+        for &lock in &held {
+            if state
+                .edges
+                .get(&lock)
+                .is_some_and(|edges| edges.contains_key(&id))
+            {
+                continue;
+            }
+            if let Some(path) = state.path(id, lock) {
+                let message = inversion(&state, &thread, lock, id, &path);
+                drop(state);
+                panic!("{message}");
+            }
+
+            let edge = Edge {
+                thread: format!("{:?}", thread.1),
+                backtrace: Backtrace::force_capture(),
+            };
+            state.edges.entry(lock).or_default().insert(id, edge);
+        }
+
+        state.held.entry(thread).or_default().push(id);
+        Held(thread)
+    }
+
+    /// Stops counting the lock as held by the thread that took it, which
+    /// isn't the current one if the guard was sent elsewhere.
+    pub(crate) fn release(&self, held: Held) {
+        let id = self.id();
+        let mut state = state();
+        let Some(locks) = state.held.get_mut(&held.0) else {
+            return;
+        };
+        if let Some(position) = locks.iter().rposition(|&lock| lock == id) {
+            locks.remove(position);
+        }
+        if locks.is_empty() {
+            state.held.remove(&held.0);
+        }
+    }
+}
+
+impl Drop for LockId {
+    fn drop(&mut self) {
+        let id = *self.id.get_mut();
+        if id == 0 {
+            return;
+        }
+        let mut state = state();
+        state.edges.remove(&id);
+        for edges in state.edges.values_mut() {
+            edges.remove(&id);
+        }
+        state.edges.retain(|_, edges| !edges.is_empty());
+        state.kinds.remove(&id);
+    }
+}
+
+/// The report for taking `new` while holding `held`, when `path` already
+/// leads from `new` to `held`.
+fn inversion(
+    state: &State,
+    thread: &Thread,
+    held: usize,
+    new: usize,
+    path: &[(usize, usize)],
+) -> String {
+    let mut message = format!(
+        "lock order inversion: {:?} is taking {} while holding {}, \
+         but {} was taken before {} earlier, so the two can deadlock\n",
+        thread.1,
+        state.name(new),
+        state.name(held),
+        state.name(new),
+        state.name(held),
+    );
+    for &(from, to) in path {
+        let edge = &state.edges[&from][&to];
+        let _ = write!(
+            message,
+            "\n{} then {}, by {}, at:\n{}\n",
+            state.name(from),
+            state.name(to),
+            edge.thread,
+            edge.backtrace
+        );
+    }
+    let _ = write!(
+        message,
+        "\n{} then {}, by {:?}, at:\n{}",
+        state.name(held),
+        state.name(new),
+        thread.1,
+        Backtrace::force_capture()
+    );
+    message
+}
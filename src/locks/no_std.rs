@@ -1,10 +1,13 @@
-use atomic_wait::{wait, wake_one};
-use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
-use std::{
-    cell::UnsafeCell,
-    ops::{Deref, DerefMut},
-    sync::atomic::AtomicU32,
-};
+use std::ops::{Deref, DerefMut};
+use std::panic::Location;
+
+use super::lockdep::{Held, LockId};
+use crate::sync::atomic::AtomicU32;
+use crate::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use crate::sync::futex::{wait, wake_one};
+use crate::sync::spin_loop;
+use crate::sync::watchdog::wait_point;
+use crate::sync::{DerefExt, UnsafeCell};
 
 pub struct Mutex<T> {
     /// 0: unlocked
@@ -12,22 +15,38 @@ pub struct Mutex<T> {
     /// 2: locked, other thread(s) waiting
     state: AtomicU32,
     value: UnsafeCell<T>,
+    lockdep: LockId,
 }
 
 impl<T> Mutex<T> {
+    #[cfg(not(loom))]
     pub const fn new(inner: T) -> Mutex<T> {
         Mutex {
             state: AtomicU32::new(0), // unlocked
             value: UnsafeCell::new(inner),
+            lockdep: LockId::new("Mutex"),
+        }
+    }
+
+    /// Loom's atomics can't be made in a `const`, so neither can the lock.
+    #[cfg(loom)]
+    pub fn new(inner: T) -> Mutex<T> {
+        Mutex {
+            state: AtomicU32::new(0), // unlocked
+            value: UnsafeCell::new(inner),
+            lockdep: LockId::new("Mutex"),
         }
     }
 
-    pub fn lock(&self) -> MutexGuard<T> {
+    #[track_caller]
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        let held = self.lockdep.acquire();
         if self.state.compare_exchange(0, 1, Acquire, Relaxed).is_err() {
             // The lock was already locked. :(
+            let _waiting = wait_point("Mutex::lock", Location::caller());
             lock_contended(&self.state);
         }
-        MutexGuard { mutex: self }
+        MutexGuard { mutex: self, held }
     }
 }
 
@@ -41,7 +60,7 @@ fn lock_contended(state: &AtomicU32) {
     // Relaxed memory ordering is fine as we don't require a happens before relationship here
     while state.load(Relaxed) == 1 && spin_count < 100 {
         spin_count += 1;
-        std::hint::spin_loop();
+        spin_loop();
     }
 
     // If after spinning the lock is unlocked, we attempt to lock it
@@ -58,6 +77,7 @@ unsafe impl<T> Sync for Mutex<T> where T: Send {}
 
 pub struct MutexGuard<'a, T> {
     mutex: &'a Mutex<T>,
+    held: Held,
 }
 
 unsafe impl<T> Send for MutexGuard<'_, T> where T: Send {}
@@ -66,13 +86,13 @@ unsafe impl<T> Sync for MutexGuard<'_, T> where T: Sync {}
 impl<T> Deref for MutexGuard<'_, T> {
     type Target = T;
     fn deref(&self) -> &T {
-        unsafe { &*self.mutex.value.get() }
+        unsafe { self.mutex.value.get_ext() }
     }
 }
 
 impl<T> DerefMut for MutexGuard<'_, T> {
     fn deref_mut(&mut self) -> &mut T {
-        unsafe { &mut *self.mutex.value.get() }
+        unsafe { self.mutex.value.get_mut_ext() }
     }
 }
 
@@ -86,6 +106,7 @@ impl<T> Drop for MutexGuard<'_, T> {
             // It's purely an optimisation
             wake_one(&self.mutex.state);
         }
+        self.mutex.lockdep.release(self.held);
     }
 }
 
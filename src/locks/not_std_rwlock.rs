@@ -1,10 +1,12 @@
-use atomic_wait::{wait, wake_all, wake_one};
-use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
-use std::{
-    cell::UnsafeCell,
-    ops::{Deref, DerefMut},
-    sync::atomic::AtomicU32,
-};
+use std::ops::{Deref, DerefMut};
+use std::panic::Location;
+
+use super::lockdep::{Held, LockId};
+use crate::sync::atomic::AtomicU32;
+use crate::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use crate::sync::futex::{wait, wake_all, wake_one};
+use crate::sync::watchdog::wait_point;
+use crate::sync::{DerefExt, UnsafeCell};
 
 pub struct RwLock<T> {
     /// The number of read locks times two, plus one if there's a writer waiting.
@@ -16,44 +18,67 @@ pub struct RwLock<T> {
     /// Incremented to wake up writers.
     writer_wake_counter: AtomicU32,
     value: UnsafeCell<T>,
+    lockdep: LockId,
 }
 unsafe impl<T> Sync for RwLock<T> where T: Send + Sync {}
 
 impl<T> RwLock<T> {
+    #[cfg(not(loom))]
     pub const fn new(value: T) -> RwLock<T> {
         RwLock {
             state: AtomicU32::new(0),
             writer_wake_counter: AtomicU32::new(0),
             value: UnsafeCell::new(value),
+            lockdep: LockId::new("RwLock"),
+        }
+    }
+
+    /// Loom's atomics can't be made in a `const`, so neither can the lock.
+    #[cfg(loom)]
+    pub fn new(value: T) -> RwLock<T> {
+        RwLock {
+            state: AtomicU32::new(0),
+            writer_wake_counter: AtomicU32::new(0),
+            value: UnsafeCell::new(value),
+            lockdep: LockId::new("RwLock"),
         }
     }
 
-    pub fn read(&self) -> ReadGuard<T> {
+    #[track_caller]
+    pub fn read(&self) -> ReadGuard<'_, T> {
+        let held = self.lockdep.acquire();
+        let caller = Location::caller();
+        let mut waiting = None;
         let mut s = self.state.load(Relaxed);
         loop {
-            if s % 2 == 0 {
+            if s.is_multiple_of(2) {
                 // Even.
                 assert!(s < u32::MAX - 2, "too many readers");
                 match self.state.compare_exchange_weak(s, s + 2, Acquire, Relaxed) {
-                    Ok(_) => return ReadGuard { rwlock: self },
+                    Ok(_) => return ReadGuard { rwlock: self, held },
                     Err(e) => s = e,
                 }
             }
-            if s % 2 == 1 {
+            if !s.is_multiple_of(2) {
                 // Odd.
+                waiting.get_or_insert_with(|| wait_point("RwLock::read", caller));
                 wait(&self.state, s);
                 s = self.state.load(Relaxed);
             }
         }
     }
 
-    pub fn write(&self) -> WriteGuard<T> {
+    #[track_caller]
+    pub fn write(&self) -> WriteGuard<'_, T> {
+        let held = self.lockdep.acquire();
+        let caller = Location::caller();
+        let mut waiting = None;
         let mut s = self.state.load(Relaxed);
         loop {
             // Try to lock if unlocked.
             if s <= 1 {
                 match self.state.compare_exchange(s, u32::MAX, Acquire, Relaxed) {
-                    Ok(_) => return WriteGuard { rwlock: self },
+                    Ok(_) => return WriteGuard { rwlock: self, held },
                     Err(e) => {
                         s = e;
                         continue;
@@ -61,7 +86,7 @@ impl<T> RwLock<T> {
                 }
             }
             // Block new readers, by making sure the state is odd.
-            if s % 2 == 0 {
+            if s.is_multiple_of(2) {
                 match self.state.compare_exchange(s, s + 1, Relaxed, Relaxed) {
                     Ok(_) => {}
                     Err(e) => {
@@ -74,9 +99,12 @@ impl<T> RwLock<T> {
             // If an unlock happens in between, it bumps the counter, so the upcoming
             // `wait(&writer_wake_counter, w)` returns immediately—no lost wake-ups.
             // We also re-check `state` so we never sleep when the lock is already free.
+            // An even state means another writer came and went, clearing our bit, and
+            // the readers since then won't wake us, so go round and set it again.
             let w = self.writer_wake_counter.load(Acquire);
             s = self.state.load(Relaxed);
-            if s >= 2 {
+            if s >= 2 && !s.is_multiple_of(2) {
+                waiting.get_or_insert_with(|| wait_point("RwLock::write", caller));
                 wait(&self.writer_wake_counter, w);
                 s = self.state.load(Relaxed);
             }
@@ -86,12 +114,13 @@ impl<T> RwLock<T> {
 
 pub struct ReadGuard<'a, T> {
     rwlock: &'a RwLock<T>,
+    held: Held,
 }
 
 impl<T> Deref for ReadGuard<'_, T> {
     type Target = T;
     fn deref(&self) -> &T {
-        unsafe { &*self.rwlock.value.get() }
+        unsafe { self.rwlock.value.get_ext() }
     }
 }
 
@@ -105,23 +134,25 @@ impl<T> Drop for ReadGuard<'_, T> {
             self.rwlock.writer_wake_counter.fetch_add(1, Release);
             wake_one(&self.rwlock.writer_wake_counter);
         }
+        self.rwlock.lockdep.release(self.held);
     }
 }
 
 pub struct WriteGuard<'a, T> {
     rwlock: &'a RwLock<T>,
+    held: Held,
 }
 
 impl<T> Deref for WriteGuard<'_, T> {
     type Target = T;
     fn deref(&self) -> &T {
-        unsafe { &*self.rwlock.value.get() }
+        unsafe { self.rwlock.value.get_ext() }
     }
 }
 
 impl<T> DerefMut for WriteGuard<'_, T> {
     fn deref_mut(&mut self) -> &mut T {
-        unsafe { &mut *self.rwlock.value.get() }
+        unsafe { self.rwlock.value.get_mut_ext() }
     }
 }
 
@@ -131,5 +162,6 @@ impl<T> Drop for WriteGuard<'_, T> {
         self.rwlock.writer_wake_counter.fetch_add(1, Release);
         wake_one(&self.rwlock.writer_wake_counter);
         wake_all(&self.rwlock.state);
+        self.rwlock.lockdep.release(self.held);
     }
 }
@@ -1,13 +1,17 @@
 use std::ops::{Deref, DerefMut};
+use std::panic::Location;
 
+use super::lockdep::{Held, LockId};
 use crate::sync::atomic::AtomicBool;
 use crate::sync::atomic::Ordering::{Acquire, Relaxed, Release};
 use crate::sync::spin_loop;
+use crate::sync::watchdog::wait_point;
 use crate::sync::{DerefExt, UnsafeCell};
 
 pub struct SpinLock<T> {
     lock: AtomicBool,
     inner: UnsafeCell<T>,
+    lockdep: LockId,
 }
 
 unsafe impl<T> Sync for SpinLock<T> where T: Send {}
@@ -17,17 +21,25 @@ impl<T> SpinLock<T> {
         SpinLock {
             lock: AtomicBool::new(false),
             inner: UnsafeCell::new(inner),
+            lockdep: LockId::new("SpinLock"),
         }
     }
 
-    pub fn lock(&self) -> Guard<T> {
+    #[track_caller]
+    pub fn lock(&self) -> Guard<'_, T> {
+        let held = self.lockdep.acquire();
+        let caller = Location::caller();
+        let mut waiting = None;
         loop {
             match self
                 .lock
                 .compare_exchange_weak(false, true, Acquire, Relaxed)
             {
-                Ok(_) => return Guard { inner: self },
-                Err(_) => spin_loop(),
+                Ok(_) => return Guard { inner: self, held },
+                Err(_) => {
+                    waiting.get_or_insert_with(|| wait_point("SpinLock::lock", caller));
+                    spin_loop()
+                }
             }
         }
     }
@@ -35,6 +47,7 @@ impl<T> SpinLock<T> {
 
 pub struct Guard<'a, T> {
     inner: &'a SpinLock<T>,
+    held: Held,
 }
 
 impl<T> Deref for Guard<'_, T> {
@@ -53,5 +66,6 @@ impl<T> DerefMut for Guard<'_, T> {
 impl<T> Drop for Guard<'_, T> {
     fn drop(&mut self) {
         self.inner.lock.store(false, Release);
+        self.inner.lockdep.release(self.held);
     }
 }
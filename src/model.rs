@@ -0,0 +1,822 @@
+//! Runs a test body under loom's model checker, under shuttle, or directly
+//! with std.
+//!
+//! This is what `concurrent_test!` expands to. The options map onto
+//! `loom::model::Builder`; anything left unset falls back to loom's own
+//! defaults, which read the usual `LOOM_*` environment variables. Without
+//! `--cfg loom` those options are accepted and ignored, and the body runs
+//! once, or as many times as `iterations` asks for.
+//!
+//! Under `--cfg shuttle` the body runs `iterations` times (1000 by default)
+//! with shuttle picking the schedule of each run, either at random or with
+//! PCT, as chosen by `scheduler`. Shuttle samples schedules rather than
+//! exploring all of them, so it scales to tests too large for loom.
+//!
+//! In std mode, `scheduler = Scheduler::Pct { depth }` runs the body
+//! `iterations` times (again 1000 by default) on real threads, with the
+//! wrappers in `crate::sync` skewing each run's schedule by PCT. The OS still
+//! has the final say, so this finds orderings a plain loop rarely hits but
+//! guarantees nothing.
+//!
+//! A std-mode body runs on the test's own thread, so it may borrow locals,
+//! watched by a thread of the crate's. If an iteration doesn't finish within
+//! `deadlock_timeout` (60 s by default), the test binary exits with a list
+//! of the body's threads and the lock or channel call each one is blocked
+//! in, rather than hanging.
+//!
+//! Every std-mode run has a seed, from the `seed` option, `LOOM_KIT_SEED`, or
+//! the clock, that PCT and the body's [`crate::rng::stream`]s draw from. It
+//! is printed if the run fails, so the same random decisions can be replayed.
+//!
+//! Under loom, a test can replay a single interleaving from a checkpoint file
+//! instead of exploring all of them, either with the `replay` option or
+//! automatically when `loom_test_artifacts/<test>.json` exists (unless
+//! `LOOM_CHECKPOINT_FILE` is set, i.e. a checkpoint is being written). The
+//! replay runs with `LOOM_LOG=trace` and `LOOM_LOCATION` turned on. A run
+//! that writes that checkpoint and finds no failure removes it again, so
+//! only failures are replayed.
+//!
+//! Before exploring, a test also replays each checkpoint in its regression
+//! corpus, `tests/loom_corpus/<test>/`, so the interleavings of bugs found
+//! before are re-checked first and with the preemption bound they were found
+//! with. Corpus replays are skipped while a checkpoint is being written.
+//!
+//! When a checkpoint is being written and loom finds a failure, the failing
+//! schedule is minimised before the test fails: the model is explored again
+//! with a preemption bound one below the failure's preemption count, until
+//! no failure is found, and the checkpoint is replaced with the failing
+//! schedule that needed the fewest preemptions. `minimize = false` turns
+//! this off.
+//!
+//! Under loom, `execution_budget` and `time_budget` stop an exploration
+//! that runs on for too long. The test then reports how many executions it
+//! explored and roughly how much of the state space that covers, and passes
+//! as inconclusive, or fails if `LOOM_KIT_INCONCLUSIVE=fail`. Unlike
+//! `max_permutations` and `max_duration`, which stop loom silently, a budget
+//! never lets an unfinished exploration pass as a complete one.
+//!
+//! A test can also be marked as expected to fail, for bodies that exist to
+//! show that the model checker catches a concurrency bug. Such a test passes
+//! only if some interleaving panics, optionally with a given message, and
+//! fails if every explored interleaving completes cleanly.
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(loom)]
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(loom)]
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(loom)]
+use crate::checkpoint::Checkpoint;
+#[cfg(not(any(loom, shuttle)))]
+use crate::rng;
+#[cfg(loom)]
+use crate::stats::{Collector, STATS_VAR, Stats};
+
+/// Whether a test that runs out of budget passes (`pass`, the default) or
+/// fails (`fail`).
+pub const INCONCLUSIVE_VAR: &str = "LOOM_KIT_INCONCLUSIVE";
+
+/// How many executions apart a budgeted exploration records its progress,
+/// when no checkpoint is being written anyway.
+#[cfg(loom)]
+const PROGRESS_INTERVAL: usize = 100;
+
+/// Runs under shuttle, or with PCT in std mode, this many times when
+/// `iterations` isn't set. So does `explore_outcomes` without loom.
+pub(crate) const SAMPLED_ITERATIONS: usize = 1_000;
+
+/// How long a std-mode iteration may run when `deadlock_timeout` isn't set.
+#[cfg(not(any(loom, shuttle)))]
+const DEADLOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How shuttle, or in std mode the wrappers in `crate::sync`, pick the
+/// thread to run at each step. Ignored under loom.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Scheduler {
+    /// Any runnable thread, uniformly at random.
+    #[default]
+    Random,
+    /// Probabilistic concurrency testing: threads run in a random priority
+    /// order that changes at `depth - 1` random points of each run. A bug
+    /// that needs `depth` events to happen in a particular order is found
+    /// with a guaranteed probability per run under shuttle. In std mode the
+    /// priorities only skew the OS's schedule, and `depth` is at most 16.
+    Pct { depth: usize },
+}
+
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(not(loom), allow(dead_code))]
+pub struct Builder {
+    max_threads: Option<usize>,
+    max_branches: Option<usize>,
+    max_permutations: Option<usize>,
+    max_duration: Option<Duration>,
+    pub(crate) preemption_bound: Option<usize>,
+    checkpoint_file: Option<PathBuf>,
+    checkpoint_interval: Option<usize>,
+    location: Option<bool>,
+    log: Option<bool>,
+    pub(crate) iterations: Option<usize>,
+    replay: Option<PathBuf>,
+    minimize: Option<bool>,
+    scheduler: Scheduler,
+    pub(crate) seed: Option<u64>,
+    execution_budget: Option<usize>,
+    time_budget: Option<Duration>,
+    stats: Option<bool>,
+    deadlock_timeout: Option<Duration>,
+    should_find_bug: bool,
+    expected_panic: Option<String>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Max number of threads in the model, including the main thread.
+    pub fn max_threads(&mut self, max_threads: usize) -> &mut Self {
+        self.max_threads = Some(max_threads);
+        self
+    }
+
+    /// Max number of thread switches in a single execution.
+    pub fn max_branches(&mut self, max_branches: usize) -> &mut Self {
+        self.max_branches = Some(max_branches);
+        self
+    }
+
+    /// Stop after exploring this many executions.
+    pub fn max_permutations(&mut self, max_permutations: usize) -> &mut Self {
+        self.max_permutations = Some(max_permutations);
+        self
+    }
+
+    /// Stop after exploring for this long.
+    pub fn max_duration(&mut self, max_duration: Duration) -> &mut Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    /// Max number of preemptions to explore per execution.
+    pub fn preemption_bound(&mut self, preemption_bound: usize) -> &mut Self {
+        self.preemption_bound = Some(preemption_bound);
+        self
+    }
+
+    /// File used to store and resume exploration progress.
+    pub fn checkpoint_file(&mut self, checkpoint_file: impl Into<PathBuf>) -> &mut Self {
+        self.checkpoint_file = Some(checkpoint_file.into());
+        self
+    }
+
+    /// How many executions to explore between checkpoint writes.
+    pub fn checkpoint_interval(&mut self, checkpoint_interval: usize) -> &mut Self {
+        self.checkpoint_interval = Some(checkpoint_interval);
+        self
+    }
+
+    /// Capture source locations for every loom operation. Very expensive.
+    pub fn location(&mut self, location: bool) -> &mut Self {
+        self.location = Some(location);
+        self
+    }
+
+    /// Log execution output to stdout.
+    pub fn log(&mut self, log: bool) -> &mut Self {
+        self.log = Some(log);
+        self
+    }
+
+    /// Replay only the interleaving stored in this checkpoint file.
+    pub fn replay(&mut self, checkpoint: impl Into<PathBuf>) -> &mut Self {
+        self.replay = Some(checkpoint.into());
+        self
+    }
+
+    /// Search for the failing schedule with the fewest preemptions when a
+    /// checkpoint is being written. On by default.
+    pub fn minimize(&mut self, minimize: bool) -> &mut Self {
+        self.minimize = Some(minimize);
+        self
+    }
+
+    /// Number of times to run the body without loom. Ignored under loom,
+    /// which decides for itself how many executions to explore.
+    pub fn iterations(&mut self, iterations: usize) -> &mut Self {
+        self.iterations = Some(iterations);
+        self
+    }
+
+    /// The scheduler to sample schedules with under shuttle or in std mode.
+    pub fn scheduler(&mut self, scheduler: Scheduler) -> &mut Self {
+        self.scheduler = scheduler;
+        self
+    }
+
+    /// The seed for the random decisions of a std-mode run, instead of
+    /// `LOOM_KIT_SEED` or a fresh one. Ignored under loom and shuttle.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Stop exploring after this many executions, as inconclusive if there
+    /// were more to explore. Ignored without loom.
+    pub fn execution_budget(&mut self, executions: usize) -> &mut Self {
+        self.execution_budget = Some(executions);
+        self
+    }
+
+    /// Stop exploring after this long, as inconclusive if there was more to
+    /// explore. Ignored without loom.
+    pub fn time_budget(&mut self, time: Duration) -> &mut Self {
+        self.time_budget = Some(time);
+        self
+    }
+
+    /// Count the most threads, branch points and preemptions of an
+    /// execution in the stats printed after exploring. Off by default,
+    /// unless `LOOM_KIT_STATS=1`, which also appends the stats to the stats
+    /// file. Ignored without loom.
+    pub fn stats(&mut self, stats: bool) -> &mut Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// How long an iteration may run in std mode before the test binary
+    /// exits as deadlocked, 60 s by default. Ignored under loom and
+    /// shuttle, which detect deadlocks themselves.
+    pub fn deadlock_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.deadlock_timeout = Some(timeout);
+        self
+    }
+
+    /// Expect the model checker to find an interleaving that panics.
+    ///
+    /// Without loom a single run rarely hits the bug, and shuttle may not
+    /// sample the schedule that does, so there a clean run passes too; only
+    /// a panic with the wrong message fails.
+    pub fn should_find_bug(&mut self) -> &mut Self {
+        self.should_find_bug = true;
+        self
+    }
+
+    /// Like [`should_find_bug`](Self::should_find_bug), but the panic
+    /// message must also contain `expected`.
+    pub fn expected_panic(&mut self, expected: impl Into<String>) -> &mut Self {
+        self.should_find_bug = true;
+        self.expected_panic = Some(expected.into());
+        self
+    }
+
+    /// Runs `f` and checks the result against the expected outcome.
+    ///
+    /// In std mode `f` runs on the calling thread, so it may borrow from it.
+    #[cfg(not(any(loom, shuttle)))]
+    pub fn check<F>(&self, f: F)
+    where
+        F: Fn(),
+    {
+        self.expect(|| self.explore(f));
+    }
+
+    /// Runs `f` and checks the result against the expected outcome.
+    #[cfg(any(loom, shuttle))]
+    pub fn check<F>(&self, f: F)
+    where
+        F: Fn() + Sync + Send + 'static,
+    {
+        self.expect(|| self.explore(f));
+    }
+
+    /// Checks how `explore` ends against the expected outcome.
+    fn expect(&self, explore: impl FnOnce() -> Exploration) {
+        if !self.should_find_bug {
+            explore();
+            return;
+        }
+
+        match panic::catch_unwind(AssertUnwindSafe(explore)) {
+            Ok(Exploration::Complete) if cfg!(loom) => {
+                panic!("expected the model checker to find a bug, but every interleaving passed")
+            }
+            Ok(_) => {}
+            Err(payload) => self.check_panic(payload),
+        }
+    }
+
+    fn check_panic(&self, payload: Box<dyn Any + Send>) {
+        let Some(expected) = &self.expected_panic else {
+            return;
+        };
+
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str));
+
+        match message {
+            Some(message) if message.contains(expected.as_str()) => {}
+            Some(message) => panic!(
+                "found a bug, but its panic message did not contain {expected:?}\n\
+                 panic message: {message:?}"
+            ),
+            None => panic!(
+                "found a bug, but its panic payload was not a string\n\
+                 expected substring: {expected:?}"
+            ),
+        }
+    }
+
+    /// Runs `f` without loom, once per iteration, perturbed by PCT if that
+    /// is the configured scheduler. Each iteration gets a seed of its own,
+    /// drawn from the run's, and must finish within the deadlock timeout.
+    #[cfg(not(any(loom, shuttle)))]
+    fn explore<F>(&self, f: F) -> Exploration
+    where
+        F: Fn(),
+    {
+        let seed = self.seed.unwrap_or_else(rng::seed);
+        let (scheduler, iterations) = (self.scheduler, self.iterations);
+        let timeout = self.deadlock_timeout.unwrap_or(DEADLOCK_TIMEOUT);
+        rng::run(seed, || {
+            crate::sync::watchdog::run(timeout, |iterated| match scheduler {
+                Scheduler::Random => {
+                    let mut seeds = rng::Rng::new(seed);
+                    for _ in 0..iterations.unwrap_or(1) {
+                        rng::join(Some(seeds.next_u64()));
+                        f();
+                        iterated();
+                    }
+                }
+                Scheduler::Pct { depth } => {
+                    let iterations = iterations.unwrap_or(SAMPLED_ITERATIONS);
+                    crate::sync::pct::run(depth, iterations, seed, || {
+                        f();
+                        iterated();
+                    });
+                }
+            })
+        });
+        Exploration::Complete
+    }
+
+    /// Runs `f` under shuttle, once per iteration, with a schedule picked by
+    /// the configured scheduler.
+    #[cfg(shuttle)]
+    fn explore<F>(&self, f: F) -> Exploration
+    where
+        F: Fn() + Sync + Send + 'static,
+    {
+        let iterations = self.iterations.unwrap_or(SAMPLED_ITERATIONS);
+        match self.scheduler {
+            Scheduler::Random => shuttle::check_random(f, iterations),
+            Scheduler::Pct { depth } => shuttle::check_pct(f, iterations, depth),
+        }
+        Exploration::Complete
+    }
+
+    /// Runs `f` under the model checker, exploring every interleaving
+    /// allowed by the configured bounds and budgets, or replaying a single
+    /// one.
+    #[cfg(loom)]
+    fn explore<F>(&self, f: F) -> Exploration
+    where
+        F: Fn() + Sync + Send + 'static,
+    {
+        use tracing_subscriber::layer::SubscriberExt;
+        use tracing_subscriber::{EnvFilter, Layer, fmt};
+
+        let replay = self.replay.clone().or_else(|| self.saved_checkpoint());
+
+        // Mirrors `loom::model`, so `LOOM_LOG` keeps working. A replay is for
+        // debugging, so it traces everything unless told otherwise.
+        let filter = match (&replay, std::env::var_os("LOOM_LOG")) {
+            (Some(_), None) => EnvFilter::new("trace"),
+            _ => EnvFilter::from_env("LOOM_LOG"),
+        };
+        let mut builder = self.to_loom();
+        let _copy = replay
+            .as_ref()
+            .map(|checkpoint| replay_from(&mut builder, checkpoint));
+        let minimize = replay.is_none() && self.minimize.unwrap_or(true);
+        let writing = builder.checkpoint_file.clone();
+
+        // Counting takes every event loom traces, which slows exploring down
+        // a lot, so it's only done when asked for or to minimise a failure
+        let detailed = replay.is_none() && self.collects_stats();
+        let collector = Collector::default();
+        let counting = detailed || (minimize && writing.is_some());
+        let subscriber = tracing_subscriber::registry()
+            .with(
+                fmt::layer()
+                    .with_test_writer()
+                    .without_time()
+                    .with_filter(filter),
+            )
+            .with(counting.then(|| collector.layer()));
+
+        // Budgets and stats are pointless for a replay. A budget's progress
+        // is read back from a checkpoint, so one is written if it isn't
+        // already.
+        let budget = replay
+            .is_none()
+            .then(|| Arc::new(Budget::new(self.execution_budget, self.time_budget)));
+        let limited = budget.as_ref().filter(|budget| budget.is_limited());
+        let _progress = limited.filter(|_| writing.is_none()).map(|_| {
+            let progress = TempFile::new(Path::new("progress.json"), "progress");
+            builder.checkpoint_file = Some(progress.0.clone());
+            builder.checkpoint_interval = self
+                .execution_budget
+                .map_or(PROGRESS_INTERVAL, |max| max.clamp(1, PROGRESS_INTERVAL));
+            progress
+        });
+
+        let f = Arc::new(f);
+        tracing::subscriber::with_default(subscriber, || {
+            if replay.is_none() && writing.is_none() {
+                self.replay_corpus(&f);
+            }
+            let Some(budget) = &budget else {
+                check(&builder, &f);
+                return Exploration::Complete;
+            };
+
+            collector.reset();
+            let budgeted = {
+                let (budget, f) = (budget.clone(), f.clone());
+                Arc::new(move || {
+                    budget.enter();
+                    f()
+                })
+            };
+            let result = panic::catch_unwind(AssertUnwindSafe(|| check(&builder, &budgeted)));
+            let preemptions = collector.preemptions();
+            let mut stats = Stats::new(
+                budget.explored(),
+                builder.preemption_bound,
+                result.is_ok(),
+                budget.start.elapsed(),
+            );
+            if detailed {
+                collector.finish(&mut stats);
+            }
+            report_stats(stats);
+
+            match result {
+                Ok(()) => {
+                    if let Some(checkpoint) = &writing {
+                        discard_passing(checkpoint);
+                    }
+                    Exploration::Complete
+                }
+                // The checkpoint is kept, so exploring can resume from it
+                Err(payload) if payload.is::<OutOfBudget>() => {
+                    budget.report(builder.checkpoint_file.as_deref());
+                    Exploration::Inconclusive
+                }
+                Err(payload) => {
+                    if let Some(checkpoint) = writing.as_ref().filter(|_| minimize) {
+                        self.minimize_failure(checkpoint, &f, preemptions, &collector);
+                    }
+                    panic::resume_unwind(payload);
+                }
+            }
+        })
+    }
+
+    /// Replays every corpus checkpoint for the running test, failing on the
+    /// first one that panics.
+    #[cfg(loom)]
+    fn replay_corpus<F>(&self, f: &Arc<F>)
+    where
+        F: Fn() + Sync + Send + 'static,
+    {
+        let Some(test) = crate::artifacts::current_test() else {
+            return;
+        };
+        let entries = crate::artifacts::corpus_entries(&test)
+            .unwrap_or_else(|e| panic!("failed to read the loom corpus for {test}: {e}"));
+
+        for entry in entries {
+            let mut builder = self.to_loom();
+            let _copy = replay_only(&mut builder, &entry);
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| check(&builder, f))) {
+                eprintln!("the interleaving in {} failed again", entry.display());
+                panic::resume_unwind(payload);
+            }
+        }
+    }
+
+    /// Replaces the failing schedule in `checkpoint` with one that needs
+    /// fewer preemptions than the `found` of the failure, if exploring with
+    /// a lower preemption bound finds one.
+    ///
+    /// The preemptions are counted by `collector` as the failing executions
+    /// run: a checkpoint only holds the path up to the last branch point
+    /// loom had options left at, as of the last execution it was saved for.
+    #[cfg(loom)]
+    fn minimize_failure<F>(
+        &self,
+        checkpoint: &Path,
+        f: &Arc<F>,
+        found: usize,
+        collector: &Collector,
+    ) where
+        F: Fn() + Sync + Send + 'static,
+    {
+        let mut fewest = found;
+        let mut minimal = None;
+
+        while fewest > 0 {
+            let bound = fewest - 1;
+            eprintln!("looking for a failing schedule with at most {bound} preemptions");
+            let attempt = TempFile::new(checkpoint, "minimize");
+            let mut builder = self.to_loom();
+            builder.preemption_bound = Some(bound);
+            builder.checkpoint_file = Some(attempt.0.clone());
+            builder.checkpoint_interval = 1;
+
+            collector.reset();
+            if panic::catch_unwind(AssertUnwindSafe(|| check(&builder, f))).is_ok() {
+                break;
+            }
+            fewest = collector.preemptions().min(bound);
+            minimal = Some(attempt);
+        }
+
+        let Some(minimal) = minimal else {
+            if found > 0 {
+                eprintln!("no failing schedule needs fewer than {found} preemptions");
+            }
+            return;
+        };
+
+        // Make sure the smaller schedule fails on its own before keeping it
+        let mut builder = self.to_loom();
+        let _copy = replay_from(&mut builder, &minimal.0);
+        if panic::catch_unwind(AssertUnwindSafe(|| check(&builder, f))).is_ok() {
+            eprintln!("the schedule with {fewest} preemptions did not fail when replayed");
+            return;
+        }
+        if let Err(e) = std::fs::copy(&minimal.0, checkpoint) {
+            eprintln!(
+                "failed to save the minimal schedule to {}: {e}",
+                checkpoint.display()
+            );
+            return;
+        }
+        eprintln!(
+            "minimised the failing schedule from {found} to {fewest} preemptions; saved to {}",
+            checkpoint.display()
+        );
+    }
+
+    /// Whether exploring counts threads, branches and preemptions, as asked
+    /// for by the `stats` option or else [`STATS_VAR`].
+    #[cfg(loom)]
+    fn collects_stats(&self) -> bool {
+        self.stats
+            .unwrap_or_else(|| std::env::var(STATS_VAR).is_ok_and(|value| value == "1"))
+    }
+
+    /// The checkpoint left behind for this test by `loom-kit checkpoint`, if
+    /// there is one and no checkpoint is being written right now.
+    #[cfg(loom)]
+    fn saved_checkpoint(&self) -> Option<PathBuf> {
+        if self.checkpoint_file.is_some() || std::env::var_os("LOOM_CHECKPOINT_FILE").is_some() {
+            return None;
+        }
+
+        let path = crate::artifacts::checkpoint_path(&crate::artifacts::current_test()?);
+        path.exists().then_some(path)
+    }
+
+    #[cfg(loom)]
+    fn to_loom(&self) -> loom::model::Builder {
+        let mut builder = loom::model::Builder::new();
+        if let Some(max_threads) = self.max_threads {
+            builder.max_threads = max_threads;
+        }
+        if let Some(max_branches) = self.max_branches {
+            builder.max_branches = max_branches;
+        }
+        if let Some(max_permutations) = self.max_permutations {
+            builder.max_permutations = Some(max_permutations);
+        }
+        if let Some(max_duration) = self.max_duration {
+            builder.max_duration = Some(max_duration);
+        }
+        if let Some(preemption_bound) = self.preemption_bound {
+            builder.preemption_bound = Some(preemption_bound);
+        }
+        if let Some(checkpoint_file) = &self.checkpoint_file {
+            builder.checkpoint_file = Some(checkpoint_file.clone());
+        }
+        if let Some(checkpoint_interval) = self.checkpoint_interval {
+            builder.checkpoint_interval = checkpoint_interval;
+        }
+        if let Some(location) = self.location {
+            builder.location = location;
+        }
+        if let Some(log) = self.log {
+            builder.log = log;
+        }
+        builder
+    }
+}
+
+/// How far [`Builder::explore`] got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exploration {
+    /// Every interleaving within the bounds was explored, or the body ran
+    /// as many times as asked.
+    Complete,
+    /// A budget ran out first.
+    #[cfg_attr(not(loom), allow(dead_code))]
+    Inconclusive,
+}
+
+/// Limits on a single exploration, checked as each execution starts. Also
+/// counts the executions for the exploration's stats.
+#[cfg(loom)]
+struct Budget {
+    executions: Option<usize>,
+    time: Option<Duration>,
+    start: std::time::Instant,
+    started: std::sync::atomic::AtomicUsize,
+    ran_out: std::sync::atomic::AtomicBool,
+}
+
+/// Unwound out of the execution that would have gone over budget.
+#[cfg(loom)]
+struct OutOfBudget;
+
+#[cfg(loom)]
+impl Budget {
+    fn new(executions: Option<usize>, time: Option<Duration>) -> Self {
+        Self {
+            executions,
+            time,
+            start: std::time::Instant::now(),
+            started: std::sync::atomic::AtomicUsize::new(0),
+            ran_out: std::sync::atomic::AtomicBool::new(false),
+        }
+    }
+
+    fn is_limited(&self) -> bool {
+        self.executions.is_some() || self.time.is_some()
+    }
+
+    /// Executions run so far, not counting one cut short by the budget.
+    fn explored(&self) -> usize {
+        use std::sync::atomic::Ordering::Relaxed;
+
+        self.started.load(Relaxed) - usize::from(self.ran_out.load(Relaxed))
+    }
+
+    /// Called as each execution starts. Unwinds with [`OutOfBudget`],
+    /// without running the panic hook, if the budget has run out.
+    fn enter(&self) {
+        let started = self
+            .started
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+            + 1;
+        let over = self.executions.is_some_and(|max| started > max)
+            || self.time.is_some_and(|max| self.start.elapsed() >= max);
+        if over {
+            self.ran_out
+                .store(true, std::sync::atomic::Ordering::Relaxed);
+            panic::resume_unwind(Box::new(OutOfBudget));
+        }
+    }
+
+    /// Reports an exploration that ran out of budget, and fails the test if
+    /// [`INCONCLUSIVE_VAR`] says so.
+    fn report(&self, progress: Option<&Path>) {
+        let explored = self.explored();
+        let elapsed = self.start.elapsed();
+        let covered = progress
+            .and_then(|path| Checkpoint::load(path).ok())
+            .map_or_else(
+                || "an unknown share".to_string(),
+                |checkpoint| format!("roughly {:.0}%", checkpoint.progress() * 100.0),
+            );
+        let message = format!(
+            "inconclusive: the budget ran out after {explored} executions in {elapsed:.2?}, \
+             covering {covered} of the interleavings"
+        );
+
+        match std::env::var(INCONCLUSIVE_VAR).as_deref() {
+            Ok("fail") => panic!("{message}"),
+            Ok("pass") | Err(_) => {
+                eprintln!("{message}; set {INCONCLUSIVE_VAR}=fail to fail such tests");
+            }
+            Ok(other) => panic!("{INCONCLUSIVE_VAR} must be pass or fail, not {other:?}"),
+        }
+    }
+}
+
+/// Prints an exploration's stats, and appends them to the stats file if
+/// [`STATS_VAR`] asks for it.
+#[cfg(loom)]
+fn report_stats(stats: Stats) {
+    eprintln!("loom: {stats}");
+    if std::env::var(STATS_VAR).is_ok_and(|value| value == "1") {
+        let path = crate::artifacts::stats_path();
+        if let Err(e) = stats.append(&path) {
+            eprintln!("failed to append stats to {}: {e}", path.display());
+        }
+    }
+    crate::stats::set_last(stats);
+}
+
+/// Runs `f` under `builder`, sharing it with other runs of the same model.
+#[cfg(loom)]
+fn check<F>(builder: &loom::model::Builder, f: &Arc<F>)
+where
+    F: Fn() + Sync + Send + 'static,
+{
+    let f = f.clone();
+    builder.check(move || f());
+}
+
+/// Removes the checkpoint `loom-kit` had this test write when every
+/// execution passed: it holds the last one explored, which would be
+/// replayed by the next run instead of exploring.
+#[cfg(loom)]
+fn discard_passing(checkpoint: &Path) {
+    let Some(test) = crate::artifacts::current_test() else {
+        return;
+    };
+    if checkpoint == crate::artifacts::checkpoint_path(&test) {
+        let _ = std::fs::remove_file(checkpoint);
+    }
+}
+
+/// Points `builder` at a copy of `checkpoint` and limits it to the one
+/// interleaving stored there, with full tracing. The copy is removed when the
+/// guard drops.
+#[cfg(loom)]
+fn replay_from(builder: &mut loom::model::Builder, checkpoint: &Path) -> TempFile {
+    eprintln!(
+        "replaying the interleaving in {}; remove it to explore every interleaving again",
+        checkpoint.display()
+    );
+
+    let copy = replay_only(builder, checkpoint);
+    builder.location = true;
+    builder.log = true;
+    copy
+}
+
+/// Like [`replay_from`], but quietly.
+#[cfg(loom)]
+fn replay_only(builder: &mut loom::model::Builder, checkpoint: &Path) -> TempFile {
+    // Loom rewrites its checkpoint file before every execution, which would
+    // clobber the original with the next interleaving.
+    let copy = TempFile::new(checkpoint, "replay");
+    std::fs::copy(checkpoint, &copy.0)
+        .unwrap_or_else(|e| panic!("failed to read checkpoint {}: {e}", checkpoint.display()));
+
+    builder.checkpoint_file = Some(copy.0.clone());
+    builder.checkpoint_interval = 1;
+    // Loom only checks the limit before starting an execution, so this stops
+    // right after the first one.
+    builder.max_permutations = Some(2);
+    copy
+}
+
+/// A scratch checkpoint file, removed when dropped.
+#[cfg(loom)]
+struct TempFile(PathBuf);
+
+#[cfg(loom)]
+impl TempFile {
+    /// A new path in the temp directory for `purpose`, named after
+    /// `checkpoint`. Nothing is created, so loom starts a fresh exploration
+    /// there, and no two are the same, as tests run in parallel and corpus
+    /// entries of different tests can share a name.
+    fn new(checkpoint: &Path, purpose: &str) -> Self {
+        static NEXT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+        let n = NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Self(std::env::temp_dir().join(format!(
+            "loom-{purpose}-{}-{n}-{}",
+            std::process::id(),
+            checkpoint.file_name().unwrap().to_string_lossy()
+        )))
+    }
+}
+
+#[cfg(loom)]
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
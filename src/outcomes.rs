@@ -0,0 +1,81 @@
+//! Collects every final outcome a test body can produce.
+//!
+//! Litmus-style tests are easier to read as "these are all the results this
+//! code can have" than as assertions inside the model. The body returns its
+//! outcome, e.g. the `(r1, r2)` pair of a store-buffering test, and the full
+//! set is asserted on afterwards.
+//!
+//! Under loom the set covers every explored interleaving. Without loom the
+//! body runs many times, on real threads or under shuttle, which only ever
+//! shows a subset.
+
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use crate::model::{Builder, SAMPLED_ITERATIONS};
+
+impl Builder {
+    /// Runs `f` like [`check`](Self::check) and returns every distinct value
+    /// it returned.
+    pub fn explore_outcomes<T, F>(&self, f: F) -> BTreeSet<T>
+    where
+        T: Ord + Send + 'static,
+        F: Fn() -> T + Sync + Send + 'static,
+    {
+        // A real mutex, not `crate::sync::Mutex`: the set has to outlive the
+        // individual loom executions.
+        let observed = Arc::new(Mutex::new(BTreeSet::new()));
+
+        let mut builder = self.clone();
+        if cfg!(not(loom)) && builder.iterations.is_none() {
+            builder.iterations(SAMPLED_ITERATIONS);
+        }
+
+        builder.check({
+            let observed = observed.clone();
+            move || {
+                let outcome = f();
+                observed.lock().unwrap().insert(outcome);
+            }
+        });
+
+        std::mem::take(&mut *observed.lock().unwrap())
+    }
+}
+
+/// [`Builder::explore_outcomes`] with the default options.
+pub fn explore_outcomes<T, F>(f: F) -> BTreeSet<T>
+where
+    T: Ord + Send + 'static,
+    F: Fn() -> T + Sync + Send + 'static,
+{
+    Builder::new().explore_outcomes(f)
+}
+
+/// Asserts that `observed` matches the `allowed` outcomes.
+///
+/// Under loom the two sets must be equal. Without loom, only outcomes outside
+/// `allowed` fail; allowed outcomes that weren't seen are printed, since real
+/// hardware may just never have hit them.
+#[track_caller]
+pub fn assert_outcomes<T>(observed: &BTreeSet<T>, allowed: &BTreeSet<T>)
+where
+    T: Ord + Debug,
+{
+    let forbidden: Vec<_> = observed.difference(allowed).collect();
+    assert!(
+        forbidden.is_empty(),
+        "observed outcomes outside the allowed set: {forbidden:?}"
+    );
+
+    let missing: Vec<_> = allowed.difference(observed).collect();
+    if cfg!(loom) {
+        assert!(
+            missing.is_empty(),
+            "allowed outcomes the model never produced: {missing:?}"
+        );
+    } else if !missing.is_empty() {
+        eprintln!("allowed outcomes not observed on this machine: {missing:?}");
+    }
+}
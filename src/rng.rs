@@ -0,0 +1,121 @@
+//! Seeds for randomized std-mode runs.
+//!
+//! Every random decision a std-mode run makes, such as the priorities and
+//! change points of a [PCT](crate::model::Scheduler::Pct) run or whatever a
+//! test body draws from [`stream`], comes from one seed. The seed is printed
+//! when the run fails, and setting `LOOM_KIT_SEED` to it makes the next run
+//! take the same decisions. Real threads still interleave as the OS pleases,
+//! so a replay is likelier to fail the same way, not certain to.
+
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The environment variable a run's seed is read from.
+pub const SEED_VAR: &str = "LOOM_KIT_SEED";
+
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+thread_local! {
+    /// The seed of the run the current thread takes part in.
+    static CURRENT: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// A splitmix64 generator: small, fast, and the same on every platform.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        mix(self.state)
+    }
+
+    /// A number in `0..bound`. `bound` must not be zero.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    /// True with probability `1 / n`.
+    pub fn one_in(&mut self, n: u64) -> bool {
+        self.below(n) == 0
+    }
+}
+
+/// splitmix64's output function, a bijection that scrambles every bit.
+pub(crate) fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// The seed from `LOOM_KIT_SEED`, or a fresh one if it isn't set.
+///
+/// # Panics
+///
+/// If `LOOM_KIT_SEED` isn't a `u64`.
+pub fn seed() -> u64 {
+    match std::env::var(SEED_VAR) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .unwrap_or_else(|_| panic!("{SEED_VAR} must be a u64, not {value:?}")),
+        Err(_) => {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+            mix(nanos ^ u64::from(std::process::id()).rotate_left(32))
+        }
+    }
+}
+
+/// Runs `f` as a run with the given seed, printing the seed if `f` panics.
+///
+/// Threads spawned through [`crate::sync::thread::spawn`] take part in the
+/// run too.
+pub fn run<R>(seed: u64, f: impl FnOnce() -> R) -> R {
+    let previous = CURRENT.replace(Some(seed));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CURRENT.set(previous);
+    result.unwrap_or_else(|payload| {
+        // A run nested in one with the same seed leaves it to the outer one
+        if previous != Some(seed) {
+            eprintln!(
+                "failed with seed {seed}; rerun with {SEED_VAR}={seed} to replay its random decisions"
+            );
+        }
+        panic::resume_unwind(payload)
+    })
+}
+
+/// Like [`run`], with the seed from [`seed`]. For stress tests outside
+/// `concurrent_test!`.
+pub fn seeded<R>(f: impl FnOnce() -> R) -> R {
+    run(seed(), f)
+}
+
+/// The seed of the run the current thread takes part in, if any.
+pub fn current() -> Option<u64> {
+    CURRENT.get()
+}
+
+/// Makes the current thread take part in the run with the given seed.
+#[cfg(not(any(loom, shuttle)))]
+pub(crate) fn join(seed: Option<u64>) {
+    CURRENT.set(seed);
+}
+
+/// The generator for stream `n` of the current run, e.g. one per thread a
+/// test spawns. Different streams give unrelated numbers.
+///
+/// Outside a run, such as under loom, where a body must make the same
+/// decisions in every execution, the seed is 0.
+pub fn stream(n: u64) -> Rng {
+    Rng::new(mix(current().unwrap_or(0) ^ mix(n)))
+}
@@ -0,0 +1,286 @@
+//! Model-based testing against a sequential reference.
+//!
+//! A [`Target`] pairs a concurrent object, such as a `SafeChannel`, with a
+//! [`Spec`] that behaves the way it should, such as a `VecDeque`:
+//!
+//! ```ignore
+//! struct Channel;
+//!
+//! impl Target for Channel {
+//!     type Spec = Queue; // a `VecDeque` whose receives block when empty
+//!     type Object = SafeChannel<usize>;
+//!     // new, apply and arbitrary_op
+//! }
+//!
+//! sequential::check_programs::<Channel>();
+//! ```
+//!
+//! [`Builder::check_programs`] generates small random [`Program`]s (a list
+//! of operations per thread), runs each one on the object under the usual
+//! checker and records what every call returned, then checks that the
+//! history is linearizable against the spec. Under loom every interleaving
+//! of every program is covered.
+//!
+//! Programs come from one seed, printed with the failing program, and
+//! `LOOM_KIT_SEED` regenerates the same ones. Programs in which some order
+//! of the operations leaves every thread waiting, such as two receives and
+//! one send, are never generated, as they would deadlock.
+//!
+//! With the `proptest` feature, `strategy` generates programs through
+//! proptest instead, which shrinks a failing one to a minimal program.
+
+use std::collections::HashSet;
+use std::fmt::{self, Debug, Display};
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::linearizability::{History, Spec};
+use crate::model::Builder;
+use crate::rng::{self, Rng};
+use crate::sync::{Arc, thread};
+
+#[cfg(feature = "proptest")]
+pub mod strategy;
+
+/// Programs are redrawn this many times when they could deadlock.
+const ATTEMPTS: usize = 1_000;
+
+/// The preemption bound programs run with under loom when none is set.
+/// Spinning and retrying loops make unbounded exploration of even small
+/// programs take minutes.
+const DEFAULT_PREEMPTION_BOUND: usize = 2;
+
+/// Schedules each program gets under shuttle when `iterations` isn't set;
+/// many programs with a few schedules each cover more than the reverse.
+const SHUTTLE_ITERATIONS: usize = 100;
+
+/// A concurrent object under test and its sequential reference.
+pub trait Target: 'static {
+    /// The reference. A new object must behave like `Spec::default()`.
+    type Spec: Spec<Op: Sync + 'static> + Default;
+    type Object: Send + Sync + 'static;
+
+    fn new() -> Self::Object;
+
+    /// Runs `op` on the object and returns what the spec's `apply` would.
+    fn apply(object: &Self::Object, op: &Op<Self>) -> Ret<Self>;
+
+    /// A random operation for a generated program.
+    fn arbitrary_op(rng: &mut Rng) -> Op<Self>;
+}
+
+/// The operations of a target.
+pub type Op<T> = <<T as Target>::Spec as Spec>::Op;
+
+/// What a target's operations return.
+pub type Ret<T> = <<T as Target>::Spec as Spec>::Ret;
+
+/// The size of the generated programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shape {
+    /// How many programs to generate.
+    pub programs: usize,
+    /// Threads per program, including the main thread.
+    pub threads: usize,
+    /// Operations per thread.
+    pub ops: usize,
+}
+
+impl Default for Shape {
+    /// Small enough for loom to explore every program exhaustively.
+    fn default() -> Self {
+        if cfg!(loom) {
+            Self {
+                programs: 20,
+                threads: 2,
+                ops: 2,
+            }
+        } else {
+            Self {
+                programs: 100,
+                threads: 3,
+                ops: 4,
+            }
+        }
+    }
+}
+
+/// A list of operations for each thread. Thread 0 runs on the main thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program<Op> {
+    pub threads: Vec<Vec<Op>>,
+}
+
+impl<Op: Clone + Debug> Program<Op> {
+    /// Draws a program of the given shape that can't deadlock under `spec`.
+    ///
+    /// # Panics
+    ///
+    /// If no such program turns up after many tries, e.g. because every
+    /// operation blocks.
+    pub fn generate<S>(
+        shape: Shape,
+        spec: &S,
+        rng: &mut Rng,
+        mut op: impl FnMut(&mut Rng) -> Op,
+    ) -> Self
+    where
+        S: Spec<Op = Op>,
+    {
+        for _ in 0..ATTEMPTS {
+            let program = Self {
+                threads: (0..shape.threads)
+                    .map(|_| (0..shape.ops).map(|_| op(&mut *rng)).collect())
+                    .collect(),
+            };
+            if program.always_finishes(spec) {
+                return program;
+            }
+        }
+        panic!("no program of {shape:?} out of {ATTEMPTS} could finish in every order");
+    }
+
+    /// Whether every order of the operations, each taking effect at once,
+    /// runs to the end from `spec` without every remaining thread waiting.
+    pub fn always_finishes<S>(&self, spec: &S) -> bool
+    where
+        S: Spec<Op = Op>,
+    {
+        let mut finishing = HashSet::new();
+        self.finishes_from(&mut vec![0; self.threads.len()], spec, &mut finishing)
+    }
+
+    /// Whether every order finishes from `positions` in each thread, given
+    /// the points already known to.
+    fn finishes_from<S>(
+        &self,
+        positions: &mut Vec<usize>,
+        spec: &S,
+        finishing: &mut HashSet<(Vec<usize>, S)>,
+    ) -> bool
+    where
+        S: Spec<Op = Op>,
+    {
+        if finishing.contains(&(positions.clone(), spec.clone())) {
+            return true;
+        }
+
+        let mut stuck = true;
+        for thread in 0..self.threads.len() {
+            let Some(op) = self.threads[thread].get(positions[thread]) else {
+                continue;
+            };
+            if spec.blocks(op) {
+                continue;
+            }
+
+            stuck = false;
+            let mut next = spec.clone();
+            next.apply(op);
+            positions[thread] += 1;
+            let finishes = self.finishes_from(positions, &next, finishing);
+            positions[thread] -= 1;
+            if !finishes {
+                return false;
+            }
+        }
+
+        // Done, or every thread left is waiting for the others
+        let done =
+            (0..self.threads.len()).all(|thread| positions[thread] == self.threads[thread].len());
+        if stuck && !done {
+            return false;
+        }
+        finishing.insert((positions.clone(), spec.clone()));
+        true
+    }
+}
+
+impl<Op: Clone + Debug + Send + Sync + 'static> Program<Op> {
+    /// Runs the program once on a new object, with a thread per list, and
+    /// asserts the calls linearize against `T::Spec`.
+    pub fn run<T>(&self)
+    where
+        T: Target<Spec: Spec<Op = Op>>,
+    {
+        let target = Arc::new(T::new());
+        let history = History::<T::Spec>::new();
+        let call = |target: &T::Object, history: &History<T::Spec>, ops: &[Op]| {
+            for op in ops {
+                history.call(op.clone(), || T::apply(target, op));
+            }
+        };
+
+        let threads: Vec<_> = self.threads[1..]
+            .iter()
+            .map(|ops| {
+                let (target, history, ops) = (target.clone(), history.clone(), ops.clone());
+                thread::spawn(move || call(&target, &history, &ops))
+            })
+            .collect();
+        call(&target, &history, &self.threads[0]);
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        history.assert_linearizable(T::Spec::default());
+    }
+}
+
+impl<Op: Debug> Display for Program<Op> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (thread, ops) in self.threads.iter().enumerate() {
+            writeln!(f, "  thread {thread}: {ops:?}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Builder {
+    /// Generates `shape.programs` random programs for `T` and checks each
+    /// one with [`check_program`](Self::check_program).
+    pub fn check_programs<T: Target>(&self, shape: Shape) {
+        let seed = self.seed.unwrap_or_else(rng::seed);
+        let mut builder = self.clone();
+        builder.seed(seed);
+
+        // Printed up front too, as a deadlock under loom aborts the process
+        eprintln!(
+            "programs: seed {seed}, {} programs of {} threads with {} operations each",
+            shape.programs, shape.threads, shape.ops
+        );
+        let mut programs = Rng::new(seed);
+        rng::run(seed, || {
+            for i in 0..shape.programs {
+                let program =
+                    Program::generate(shape, &T::Spec::default(), &mut programs, T::arbitrary_op);
+                let check = || builder.check_program::<T>(&program);
+                if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(check)) {
+                    eprintln!("program {} of {} failed:\n{program}", i + 1, shape.programs);
+                    panic::resume_unwind(payload);
+                }
+            }
+        });
+    }
+
+    /// Runs `program` on a new object like [`check`](Self::check), asserting
+    /// it linearizes against `T::Spec`. Under loom the preemption bound
+    /// defaults to 2, and under shuttle the program runs 100 times unless
+    /// `iterations` says otherwise.
+    pub fn check_program<T: Target>(&self, program: &Program<Op<T>>) {
+        let mut builder = self.clone();
+        if cfg!(loom) && builder.preemption_bound.is_none() {
+            builder.preemption_bound(DEFAULT_PREEMPTION_BOUND);
+        }
+        if cfg!(shuttle) && builder.iterations.is_none() {
+            builder.iterations(SHUTTLE_ITERATIONS);
+        }
+
+        let program = program.clone();
+        builder.check(move || program.run::<T>());
+    }
+}
+
+/// [`Builder::check_programs`] with the default options and shape.
+pub fn check_programs<T: Target>() {
+    Builder::new().check_programs::<T>(Shape::default());
+}
@@ -0,0 +1,113 @@
+//! proptest strategies for [`Program`]s.
+//!
+//! [`programs`] generates the same kind of programs as
+//! [`Builder::check_programs`], but through proptest, so a failing one is
+//! shrunk: threads and operations are dropped until every smaller program
+//! passes. [`Builder::proptest_programs`], or [`proptest_check_programs`]
+//! with the defaults, runs the whole loop, or the strategy can go in a
+//! `proptest!` block of its own:
+//!
+//! ```ignore
+//! proptest! {
+//!     #[test]
+//!     fn channel(program in strategy::programs::<Channel>(Shape::default())) {
+//!         Builder::new().check_program::<Channel>(&program);
+//!     }
+//! }
+//! ```
+//!
+//! Shrinking reruns the failing case many times, so under std and shuttle,
+//! which only sample schedules, it stops early when a smaller program
+//! happens to pass. A deadlock under loom aborts the process before
+//! anything can be shrunk.
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::test_runner::{Config, RngAlgorithm, TestError, TestRng, TestRunner};
+
+use super::{Op, Program, Shape, Target};
+use crate::linearizability::Spec;
+use crate::model::Builder;
+use crate::rng::{self, Rng};
+
+/// Any operation of `T`, drawn by [`Target::arbitrary_op`] from a seed. It
+/// shrinks towards the operation seed 0 gives.
+pub fn op<T: Target>() -> impl Strategy<Value = Op<T>> {
+    any::<u64>().prop_map(|seed| T::arbitrary_op(&mut Rng::new(seed)))
+}
+
+/// Programs of up to `shape.threads` threads with up to `shape.ops`
+/// operations each, built from [`op`].
+pub fn programs<T: Target>(shape: Shape) -> impl Strategy<Value = Program<Op<T>>> {
+    programs_with::<T::Spec>(shape, op::<T>())
+}
+
+/// Programs of up to `shape.threads` threads with up to `shape.ops`
+/// operations each, drawn from `op`. Programs that could deadlock under `S`
+/// are rejected, also while shrinking.
+pub fn programs_with<S>(
+    shape: Shape,
+    op: impl Strategy<Value = S::Op>,
+) -> impl Strategy<Value = Program<S::Op>>
+where
+    S: Spec + Default,
+{
+    vec(vec(op, 0..=shape.ops), 1..=shape.threads)
+        .prop_map(|threads| Program { threads })
+        .prop_filter("could deadlock", |program| {
+            program.always_finishes(&S::default())
+        })
+}
+
+impl Builder {
+    /// Checks `shape.programs` programs from [`programs`] with
+    /// [`check_program`](Self::check_program), and panics with the smallest
+    /// failing program proptest shrinks a failure to.
+    pub fn proptest_programs<T: Target>(&self, shape: Shape) {
+        let seed = self.seed.unwrap_or_else(rng::seed);
+        let mut builder = self.clone();
+        builder.seed(seed);
+
+        eprintln!(
+            "proptest: seed {seed}, {} programs of up to {} threads with up to {} operations each",
+            shape.programs, shape.threads, shape.ops
+        );
+        let config = Config {
+            cases: shape.programs.try_into().unwrap_or(u32::MAX),
+            failure_persistence: None,
+            ..Config::default()
+        };
+        let mut runner = TestRunner::new_with_rng(config, test_rng(seed));
+
+        rng::run(seed, || {
+            let result = runner.run(&programs::<T>(shape), |program| {
+                builder.check_program::<T>(&program);
+                Ok(())
+            });
+            match result {
+                Ok(()) => {}
+                Err(TestError::Fail(reason, program)) => {
+                    panic!("minimal failing program:\n{program}{reason}")
+                }
+                Err(TestError::Abort(reason)) => panic!("proptest gave up: {reason}"),
+            }
+        });
+    }
+}
+
+/// [`Builder::proptest_programs`] with the default options and shape; the
+/// proptest counterpart of [`check_programs`](super::check_programs), which
+/// shrinks a failing program before panicking.
+pub fn proptest_check_programs<T: Target>() {
+    Builder::new().proptest_programs::<T>(Shape::default());
+}
+
+/// proptest's generator, seeded from a run seed.
+fn test_rng(seed: u64) -> TestRng {
+    let mut bytes = [0; 32];
+    let mut rng = Rng::new(seed);
+    for chunk in bytes.chunks_mut(8) {
+        chunk.copy_from_slice(&rng.next_u64().to_le_bytes());
+    }
+    TestRng::from_seed(RngAlgorithm::ChaCha, &bytes)
+}
@@ -0,0 +1,320 @@
+//! Statistics on a test's loom exploration.
+//!
+//! After exploring, a test prints how many executions it took, the wall time
+//! and the preemption bound. With the `stats` option or `LOOM_KIT_STATS=1`
+//! it also counts the most threads, branch points and preemptions any one
+//! execution had, and whether the preemption bound was reached. With
+//! `LOOM_KIT_STATS=1` the record is also appended to
+//! `loom_test_artifacts/stats.jsonl`, one JSON object per line, so the size
+//! of each test's state space can be tracked across changes.
+//!
+//! Loom keeps its exploration to itself, so the per-execution counts are
+//! taken from the events it traces: every spawn, every scheduling point and
+//! every atomic load that picks a store. Spurious wakeups aren't traced, so
+//! models that allow them may take a few more branches than reported. Loom
+//! traces a lot, so exploring takes far longer with the counts than without.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Set to `1` to collect every test's stats and append them to
+/// [`crate::artifacts::stats_path`].
+pub const STATS_VAR: &str = "LOOM_KIT_STATS";
+
+thread_local! {
+    /// The stats of the last exploration that ran on this thread.
+    static LAST: RefCell<Option<Stats>> = const { RefCell::new(None) };
+}
+
+/// The stats of the last loom exploration on the current thread, e.g. to
+/// check a model's state space from the test itself.
+pub fn last() -> Option<Stats> {
+    LAST.with_borrow(Clone::clone)
+}
+
+/// Records `stats` as the current thread's [`last`].
+#[cfg(loom)]
+pub(crate) fn set_last(stats: Stats) {
+    LAST.set(Some(stats));
+}
+
+/// One exploration of one test.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    /// The test, as the test harness names it, if it ran under one.
+    pub test: Option<String>,
+    /// When the exploration finished, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Executions explored.
+    pub executions: usize,
+    /// The most threads in any execution, including the main thread. This
+    /// and the other counts are `None` unless they were collected.
+    pub max_threads: Option<usize>,
+    /// The most branch points in any execution, the count loom's
+    /// `max_branches` limits.
+    pub max_branches: Option<usize>,
+    /// The most preemptions in any execution.
+    pub max_preemptions: Option<usize>,
+    /// The preemption bound, if there was one.
+    pub preemption_bound: Option<usize>,
+    /// Whether some execution used every preemption the bound allows, in
+    /// which case the bound may have pruned interleavings.
+    pub bound_reached: Option<bool>,
+    /// Whether the exploration finished, rather than failing or running out
+    /// of budget.
+    pub complete: bool,
+    /// How long the exploration took.
+    #[serde(rename = "wall_time_secs", with = "seconds")]
+    pub wall_time: Duration,
+}
+
+impl Stats {
+    /// The stats of an exploration, without the counts.
+    #[cfg(loom)]
+    pub(crate) fn new(
+        executions: usize,
+        preemption_bound: Option<usize>,
+        complete: bool,
+        wall_time: Duration,
+    ) -> Stats {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Stats {
+            test: crate::artifacts::current_test(),
+            timestamp,
+            executions,
+            max_threads: None,
+            max_branches: None,
+            max_preemptions: None,
+            preemption_bound,
+            bound_reached: None,
+            complete,
+            wall_time,
+        }
+    }
+
+    /// Appends these stats to `path` as one line of JSON, creating the file
+    /// and its directory if needed.
+    pub fn append(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut line = serde_json::to_string(self)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(line.as_bytes())
+    }
+
+    /// Reads every record in a stats file written by [`append`](Self::append).
+    pub fn read_all(path: impl AsRef<Path>) -> io::Result<Vec<Self>> {
+        BufReader::new(fs::File::open(path)?)
+            .lines()
+            .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect()
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} executions in {:.2?}{}",
+            self.executions,
+            self.wall_time,
+            if self.complete { "" } else { " (incomplete)" },
+        )?;
+        if let (Some(threads), Some(branches), Some(preemptions)) =
+            (self.max_threads, self.max_branches, self.max_preemptions)
+        {
+            write!(
+                f,
+                "; at most {threads} threads, {branches} branches and {preemptions} preemptions"
+            )?;
+        }
+        match (self.preemption_bound, self.bound_reached) {
+            (Some(bound), Some(true)) => write!(
+                f,
+                "; the preemption bound of {bound} was reached and may have pruned interleavings"
+            ),
+            (Some(bound), Some(false)) => {
+                write!(f, "; the preemption bound of {bound} was never reached")
+            }
+            (Some(bound), None) => write!(f, " with a preemption bound of {bound}"),
+            (None, _) => Ok(()),
+        }
+    }
+}
+
+/// Durations as fractional seconds, which are easier to plot than serde's
+/// default of seconds and nanoseconds.
+mod seconds {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(duration.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let seconds = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(seconds).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Counts threads, branch points and preemptions per execution from loom's
+/// trace events.
+#[cfg(loom)]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Collector(std::sync::Arc<std::sync::Mutex<Counts>>);
+
+#[cfg(loom)]
+#[derive(Debug, Default)]
+struct Counts {
+    /// Threads spawned so far in the current execution.
+    spawned: usize,
+    branches: usize,
+    preemptions: usize,
+    /// Whether the last operation blocked the running thread.
+    blocked: bool,
+    max_threads: usize,
+    max_branches: usize,
+    max_preemptions: usize,
+}
+
+#[cfg(loom)]
+impl Collector {
+    /// A tracing layer that feeds this collector. It wants loom's trace
+    /// events whatever `LOOM_LOG` says.
+    pub(crate) fn layer<S>(&self) -> impl tracing_subscriber::Layer<S> + use<S>
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        use tracing_subscriber::Layer;
+        use tracing_subscriber::filter::Targets;
+
+        self.clone()
+            .with_filter(Targets::new().with_target("loom", tracing::Level::TRACE))
+    }
+
+    /// Forgets everything seen so far, e.g. during corpus replays.
+    pub(crate) fn reset(&self) {
+        *self.0.lock().unwrap() = Counts::default();
+    }
+
+    /// The preemptions of the execution running, or of the last one if the
+    /// exploration ended, e.g. with the execution that failed.
+    pub(crate) fn preemptions(&self) -> usize {
+        self.0.lock().unwrap().preemptions
+    }
+
+    /// Adds the counts of the exploration since the last reset to `stats`.
+    pub(crate) fn finish(&self, stats: &mut Stats) {
+        let mut counts = self.0.lock().unwrap();
+        counts.end_execution();
+        stats.max_threads = Some(counts.max_threads);
+        stats.max_branches = Some(counts.max_branches);
+        stats.max_preemptions = Some(counts.max_preemptions);
+        stats.bound_reached = Some(
+            stats
+                .preemption_bound
+                .is_some_and(|bound| counts.max_preemptions >= bound),
+        );
+    }
+}
+
+#[cfg(loom)]
+impl Counts {
+    fn end_execution(&mut self) {
+        self.max_threads = self.max_threads.max(self.spawned + 1);
+        self.max_branches = self.max_branches.max(self.branches);
+        self.max_preemptions = self.max_preemptions.max(self.preemptions);
+        self.spawned = 0;
+        self.branches = 0;
+        self.preemptions = 0;
+        self.blocked = false;
+    }
+}
+
+#[cfg(loom)]
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for Collector {
+    fn on_new_span(
+        &self,
+        attrs: &tracing::span::Attributes<'_>,
+        _id: &tracing::span::Id,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        // Loom opens an `iter` span as each execution starts
+        if attrs.metadata().name() == "iter" {
+            self.0.lock().unwrap().end_execution();
+        }
+    }
+
+    fn on_event(
+        &self,
+        event: &tracing::Event<'_>,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+
+        let mut counts = self.0.lock().unwrap();
+        match fields.message.as_str() {
+            "spawn" => counts.spawned += 1,
+            // Traced before the scheduling point of an operation that may
+            // block the running thread
+            "Object::branch_acquire" | "Object::branch_disable" => counts.blocked = fields.blocked,
+            // The scheduling point after an operation. Switching away from a
+            // thread that could carry on is a preemption.
+            "branch" => {
+                counts.branches += 1;
+                if fields.switch && !counts.blocked {
+                    counts.preemptions += 1;
+                }
+                counts.blocked = false;
+            }
+            // A thread with an unpark saved consumes it rather than parking
+            "park" if !fields.unparked => counts.branches += 1,
+            // Scheduling points where the running thread can't carry on
+            "yield_now" | "thread_done: terminate" => counts.branches += 1,
+            // Picking the store a load reads is a branch point of its own
+            "Atomic::load" | "Atomic::rmw" => counts.branches += 1,
+            _ => {}
+        }
+    }
+}
+
+/// The fields of a loom event that [`Collector`] looks at.
+#[cfg(loom)]
+#[derive(Default)]
+struct Fields {
+    message: String,
+    switch: bool,
+    blocked: bool,
+    unparked: bool,
+}
+
+#[cfg(loom)]
+impl tracing::field::Visit for Fields {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{value:?}"),
+            "switch" => self.switch = format!("{value:?}") == "true",
+            "is_locked" | "disable" => self.blocked = format!("{value:?}") == "true",
+            "active.state" => self.unparked = format!("{value:?}").contains("unparked: true"),
+            _ => {}
+        }
+    }
+}
@@ -1,3 +1,23 @@
+//! The concurrency primitives tests are written against: loom's under
+//! `--cfg loom`, shuttle's under `--cfg shuttle`, and std's otherwise.
+//!
+//! Shuttle doesn't model `UnsafeCell`, so std's is used with it. In std mode
+//! [`atomic`] and [`thread`] are thin wrappers around std's that let a
+//! [`Scheduler::Pct`](crate::model::Scheduler::Pct) run perturb the schedule;
+//! under loom [`atomic`] wraps loom's, to trace what loom leaves out.
+
+pub mod futex;
+#[cfg(not(any(loom, shuttle)))]
+pub(crate) mod pct;
+#[cfg(not(any(loom, shuttle)))]
+pub(crate) mod watchdog;
+
+#[cfg(all(loom, shuttle))]
+compile_error!("`--cfg loom` and `--cfg shuttle` can't be used together");
+
+#[cfg(not(shuttle))]
+pub mod atomic;
+
 #[cfg(loom)]
 pub use loom::cell::*;
 #[cfg(loom)]
@@ -7,17 +27,56 @@ pub use loom::sync::*;
 #[cfg(loom)]
 pub use loom::thread;
 
-#[cfg(not(loom))]
+#[cfg(shuttle)]
+pub use shuttle::hint::spin_loop;
+#[cfg(shuttle)]
+pub use shuttle::sync::*;
+#[cfg(shuttle)]
+pub use shuttle::thread;
+#[cfg(shuttle)]
+pub use std::cell::*;
+
+#[cfg(not(any(loom, shuttle)))]
 pub use std::cell::*;
-#[cfg(not(loom))]
+#[cfg(not(any(loom, shuttle)))]
 pub use std::hint::spin_loop;
-#[cfg(not(loom))]
+#[cfg(not(any(loom, shuttle)))]
 pub use std::sync::*;
-#[cfg(not(loom))]
-pub use std::thread;
+#[cfg(not(any(loom, shuttle)))]
+pub mod thread;
 
+/// Loom and shuttle find deadlocks themselves, so wait points are only
+/// tracked in std mode.
+#[cfg(any(loom, shuttle))]
+pub(crate) mod watchdog {
+    use std::panic::Location;
+
+    pub(crate) struct WaitPoint;
+
+    #[inline]
+    pub(crate) fn wait_point(
+        _what: &'static str,
+        _location: &'static Location<'static>,
+    ) -> WaitPoint {
+        WaitPoint
+    }
+}
+
+/// Borrows the contents of an `UnsafeCell` the same way whichever
+/// primitives are in use. Under loom every borrow is traced, so it shows up
+/// in `loom-kit trace` logs next to the atomic accesses.
 pub trait DerefExt<T> {
+    /// # Safety
+    ///
+    /// No mutable reference to the contents may be alive for as long as the
+    /// returned reference is.
     unsafe fn get_ext(&self) -> &T;
+
+    /// # Safety
+    ///
+    /// No other reference to the contents may be alive for as long as the
+    /// returned reference is.
+    #[allow(clippy::mut_from_ref)]
     unsafe fn get_mut_ext(&self) -> &mut T;
 }
 
@@ -26,6 +85,7 @@ impl<T> DerefExt<T> for UnsafeCell<T> {
         unsafe {
             #[cfg(loom)]
             {
+                tracing::trace!(cell = ?std::ptr::from_ref(self), "UnsafeCell::with");
                 self.get().with(|ptr| &*ptr)
             }
             #[cfg(not(loom))]
@@ -39,6 +99,7 @@ impl<T> DerefExt<T> for UnsafeCell<T> {
         unsafe {
             #[cfg(loom)]
             {
+                tracing::trace!(cell = ?std::ptr::from_ref(self), "UnsafeCell::with_mut");
                 self.get_mut().with(|ptr| &mut *ptr)
             }
             #[cfg(not(loom))]
@@ -0,0 +1,314 @@
+//! std's atomics, with a [PCT](super::pct) schedule point before every
+//! operation, or loom's under `--cfg loom`.
+//!
+//! Each type wraps the std or loom atomic of the same name and has the same
+//! methods. Outside a PCT run the schedule point is a thread-local read, so
+//! plain std-mode tests behave as before.
+//!
+//! Loom traces a read-modify-write before it knows whether it will store,
+//! so under loom each one also traces whether it did, for
+//! [`crate::trace`]: a failed compare-exchange only reads.
+
+use std::fmt;
+
+#[cfg(loom)]
+use loom::sync::atomic as inner;
+#[cfg(not(loom))]
+use std::sync::atomic as inner;
+
+pub use std::sync::atomic::Ordering;
+
+#[cfg(not(loom))]
+use super::pct::schedule_point;
+
+/// Loom's operations are schedule points already.
+#[cfg(loom)]
+#[inline]
+fn schedule_point() {}
+
+/// Traces whether a read-modify-write stored, after loom's own trace of it.
+#[cfg(loom)]
+fn rmw_done(stored: bool) {
+    tracing::trace!(stored, "Atomic::rmw done");
+}
+
+#[cfg(not(loom))]
+#[inline]
+fn rmw_done(_stored: bool) {}
+
+/// [`std::sync::atomic::fence`], after a schedule point.
+#[cfg_attr(loom, track_caller)]
+pub fn fence(order: Ordering) {
+    schedule_point();
+    inner::fence(order);
+}
+
+/// [`std::sync::atomic::compiler_fence`]. Not a schedule point, as it emits
+/// no instruction.
+#[cfg(not(loom))]
+pub fn compiler_fence(order: Ordering) {
+    inner::compiler_fence(order);
+}
+
+/// The operations every atomic type has.
+macro_rules! atomic {
+    ($(#[$meta:meta])* $name:ident, $value:ty) => {
+        $(#[$meta])*
+        #[derive(Default)]
+        #[repr(transparent)]
+        pub struct $name(inner::$name);
+
+        impl $name {
+            #[cfg(not(loom))]
+            pub const fn new(value: $value) -> Self {
+                Self(inner::$name::new(value))
+            }
+
+            #[cfg(loom)]
+            #[track_caller]
+            pub fn new(value: $value) -> Self {
+                Self(inner::$name::new(value))
+            }
+
+            /// The wrapped std atomic, for APIs that need one. Accesses
+            /// through it aren't schedule points.
+            #[cfg(not(loom))]
+            pub fn as_std(&self) -> &inner::$name {
+                &self.0
+            }
+
+            #[cfg(not(loom))]
+            pub fn get_mut(&mut self) -> &mut $value {
+                self.0.get_mut()
+            }
+
+            #[cfg_attr(loom, track_caller)]
+            pub fn into_inner(self) -> $value {
+                self.0.into_inner()
+            }
+
+            #[cfg_attr(loom, track_caller)]
+            pub fn load(&self, order: Ordering) -> $value {
+                schedule_point();
+                self.0.load(order)
+            }
+
+            #[cfg_attr(loom, track_caller)]
+            pub fn store(&self, value: $value, order: Ordering) {
+                schedule_point();
+                self.0.store(value, order)
+            }
+
+            #[cfg_attr(loom, track_caller)]
+            pub fn swap(&self, value: $value, order: Ordering) -> $value {
+                schedule_point();
+                let previous = self.0.swap(value, order);
+                rmw_done(true);
+                previous
+            }
+
+            #[cfg_attr(loom, track_caller)]
+            pub fn compare_exchange(
+                &self,
+                current: $value,
+                new: $value,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$value, $value> {
+                schedule_point();
+                let result = self.0.compare_exchange(current, new, success, failure);
+                rmw_done(result.is_ok());
+                result
+            }
+
+            #[cfg_attr(loom, track_caller)]
+            pub fn compare_exchange_weak(
+                &self,
+                current: $value,
+                new: $value,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$value, $value> {
+                schedule_point();
+                let result = self.0.compare_exchange_weak(current, new, success, failure);
+                rmw_done(result.is_ok());
+                result
+            }
+
+            #[cfg_attr(loom, track_caller)]
+            pub fn fetch_update<F>(
+                &self,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                mut f: F,
+            ) -> Result<$value, $value>
+            where
+                F: FnMut($value) -> Option<$value>,
+            {
+                // Every attempt is an operation of its own
+                let mut previous = self.load(fetch_order);
+                while let Some(next) = f(previous) {
+                    match self.compare_exchange_weak(previous, next, set_order, fetch_order) {
+                        Ok(previous) => return Ok(previous),
+                        Err(current) => previous = current,
+                    }
+                }
+                Err(previous)
+            }
+        }
+
+        impl From<$value> for $name {
+            fn from(value: $value) -> Self {
+                Self::new(value)
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&self.0, f)
+            }
+        }
+    };
+}
+
+/// Read-modify-write operations, each after a schedule point.
+macro_rules! fetch {
+    ($name:ident, $value:ty, $($op:ident),*) => {
+        impl $name {
+            $(
+                #[cfg_attr(loom, track_caller)]
+                pub fn $op(&self, value: $value, order: Ordering) -> $value {
+                    schedule_point();
+                    let previous = self.0.$op(value, order);
+                    rmw_done(true);
+                    previous
+                }
+            )*
+        }
+    };
+}
+
+macro_rules! integer {
+    ($($name:ident: $value:ty),*) => {
+        $(
+            atomic!(
+                #[doc = concat!("[`std::sync::atomic::", stringify!($name), "`], with schedule points.")]
+                $name, $value
+            );
+            fetch!($name, $value, fetch_add, fetch_sub, fetch_and, fetch_nand, fetch_or, fetch_xor, fetch_max, fetch_min);
+        )*
+    };
+}
+
+atomic!(
+    /// [`std::sync::atomic::AtomicBool`], with schedule points.
+    AtomicBool, bool
+);
+fetch!(AtomicBool, bool, fetch_and, fetch_nand, fetch_or, fetch_xor);
+
+integer!(
+    AtomicI8: i8,
+    AtomicI16: i16,
+    AtomicI32: i32,
+    AtomicI64: i64,
+    AtomicIsize: isize,
+    AtomicU8: u8,
+    AtomicU16: u16,
+    AtomicU32: u32,
+    AtomicU64: u64,
+    AtomicUsize: usize
+);
+
+/// [`std::sync::atomic::AtomicPtr`], with schedule points.
+#[repr(transparent)]
+pub struct AtomicPtr<T>(inner::AtomicPtr<T>);
+
+impl<T> AtomicPtr<T> {
+    #[cfg(not(loom))]
+    pub const fn new(ptr: *mut T) -> Self {
+        Self(inner::AtomicPtr::new(ptr))
+    }
+
+    #[cfg(loom)]
+    #[track_caller]
+    pub fn new(ptr: *mut T) -> Self {
+        Self(inner::AtomicPtr::new(ptr))
+    }
+
+    /// The wrapped std atomic, for APIs that need one. Accesses through it
+    /// aren't schedule points.
+    #[cfg(not(loom))]
+    pub fn as_std(&self) -> &inner::AtomicPtr<T> {
+        &self.0
+    }
+
+    #[cfg(not(loom))]
+    pub fn get_mut(&mut self) -> &mut *mut T {
+        self.0.get_mut()
+    }
+
+    #[cfg_attr(loom, track_caller)]
+    pub fn into_inner(self) -> *mut T {
+        self.0.into_inner()
+    }
+
+    #[cfg_attr(loom, track_caller)]
+    pub fn load(&self, order: Ordering) -> *mut T {
+        schedule_point();
+        self.0.load(order)
+    }
+
+    #[cfg_attr(loom, track_caller)]
+    pub fn store(&self, ptr: *mut T, order: Ordering) {
+        schedule_point();
+        self.0.store(ptr, order)
+    }
+
+    #[cfg_attr(loom, track_caller)]
+    pub fn swap(&self, ptr: *mut T, order: Ordering) -> *mut T {
+        schedule_point();
+        let previous = self.0.swap(ptr, order);
+        rmw_done(true);
+        previous
+    }
+
+    #[cfg_attr(loom, track_caller)]
+    pub fn compare_exchange(
+        &self,
+        current: *mut T,
+        new: *mut T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<*mut T, *mut T> {
+        schedule_point();
+        let result = self.0.compare_exchange(current, new, success, failure);
+        rmw_done(result.is_ok());
+        result
+    }
+
+    #[cfg_attr(loom, track_caller)]
+    pub fn compare_exchange_weak(
+        &self,
+        current: *mut T,
+        new: *mut T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<*mut T, *mut T> {
+        schedule_point();
+        let result = self.0.compare_exchange_weak(current, new, success, failure);
+        rmw_done(result.is_ok());
+        result
+    }
+}
+
+impl<T> Default for AtomicPtr<T> {
+    fn default() -> Self {
+        Self(inner::AtomicPtr::default())
+    }
+}
+
+impl<T> fmt::Debug for AtomicPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
@@ -0,0 +1,188 @@
+//! Futex-style `wait` / `wake_one` / `wake_all` on an `AtomicU32`.
+//!
+//! Outside of loom this is just `atomic_wait`, behind a PCT schedule point.
+//! Under loom the kernel's wait queue is modelled with loom primitives, so the
+//! model checker sees every block and wake-up and can explore them like any
+//! other operation. Under shuttle it is modelled the same way with shuttle's
+//! primitives.
+
+#[cfg(not(any(loom, shuttle)))]
+pub use self::os::{wait, wake_all, wake_one};
+
+#[cfg(loom)]
+pub use self::model::{wait, wake_all, wake_one};
+
+#[cfg(shuttle)]
+pub use self::shuttle_model::{wait, wake_all, wake_one};
+
+#[cfg(not(any(loom, shuttle)))]
+mod os {
+    use crate::sync::atomic::AtomicU32;
+    use crate::sync::pct::schedule_point;
+
+    /// Blocks while `atomic` holds `value`, or returns spuriously.
+    pub fn wait(atomic: &AtomicU32, value: u32) {
+        schedule_point();
+        atomic_wait::wait(atomic.as_std(), value);
+    }
+
+    /// Wakes at most one thread blocked in [`wait`] on `atomic`.
+    pub fn wake_one(atomic: &AtomicU32) {
+        schedule_point();
+        atomic_wait::wake_one(atomic.as_std());
+    }
+
+    /// Wakes every thread blocked in [`wait`] on `atomic`.
+    pub fn wake_all(atomic: &AtomicU32) {
+        schedule_point();
+        atomic_wait::wake_all(atomic.as_std());
+    }
+}
+
+#[cfg(loom)]
+mod model {
+    use crate::sync::atomic::{AtomicU32, Ordering::Relaxed};
+    use crate::sync::{Arc, Mutex, Notify};
+
+    /// A thread blocked in [`wait`], keyed by the address of the atomic.
+    struct Waiter {
+        addr: usize,
+        notify: Arc<Notify>,
+    }
+
+    loom::lazy_static! {
+        /// The modelled kernel wait queue, recreated for every execution.
+        static ref QUEUE: Mutex<Vec<Waiter>> = Mutex::new(Vec::new());
+    }
+
+    loom::thread_local! {
+        /// Each thread parks on its own `Notify`. Loom lets a `Notify` wake
+        /// spuriously once, so reusing it bounds spurious wake-ups to one per
+        /// thread per execution instead of one per loop iteration.
+        static PARKER: Arc<Notify> = Arc::new(Notify::new());
+    }
+
+    fn addr(atomic: &AtomicU32) -> usize {
+        atomic as *const AtomicU32 as usize
+    }
+
+    /// Blocks while `atomic` holds `value`.
+    ///
+    /// Like a real futex, the comparison and the enqueue happen atomically
+    /// with respect to the wake functions, so a wake-up issued after the
+    /// value changed can never be lost. The wait may also return spuriously:
+    /// loom's `Notify` treats that as a branch, so both outcomes are explored.
+    /// A wake-up that arrives after a spurious return is kept by the parker and
+    /// ends the thread's next wait early, which a futex is also allowed to do.
+    pub fn wait(atomic: &AtomicU32, value: u32) {
+        let notify = {
+            let mut queue = QUEUE.lock().unwrap();
+            if atomic.load(Relaxed) != value {
+                return;
+            }
+            let notify = PARKER.with(Arc::clone);
+            queue.push(Waiter {
+                addr: addr(atomic),
+                notify: notify.clone(),
+            });
+            notify
+        };
+
+        notify.wait();
+
+        // After a spurious wake-up we are still queued; take ourselves out so
+        // a later wake isn't spent on a thread that is no longer waiting.
+        QUEUE
+            .lock()
+            .unwrap()
+            .retain(|waiter| !Arc::ptr_eq(&waiter.notify, &notify));
+    }
+
+    /// Wakes at most one thread blocked in [`wait`] on `atomic`.
+    pub fn wake_one(atomic: &AtomicU32) {
+        let mut queue = QUEUE.lock().unwrap();
+        let addr = addr(atomic);
+        if let Some(index) = queue.iter().position(|waiter| waiter.addr == addr) {
+            queue.remove(index).notify.notify();
+        }
+    }
+
+    /// Wakes every thread blocked in [`wait`] on `atomic`.
+    pub fn wake_all(atomic: &AtomicU32) {
+        let mut queue = QUEUE.lock().unwrap();
+        let addr = addr(atomic);
+        queue.retain(|waiter| {
+            if waiter.addr == addr {
+                waiter.notify.notify();
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+#[cfg(shuttle)]
+mod shuttle_model {
+    use crate::sync::atomic::{AtomicU32, Ordering::Relaxed};
+    use crate::sync::{Condvar, Mutex};
+
+    /// The blocked threads, as the address of the atomic each waits on and
+    /// a ticket that stays in the queue until the thread is woken.
+    #[derive(Default)]
+    struct Queue {
+        waiters: Vec<(usize, usize)>,
+        next_ticket: usize,
+    }
+
+    shuttle::lazy_static! {
+        /// The modelled kernel wait queue, recreated for every execution.
+        static ref QUEUE: Mutex<Queue> = Mutex::new(Queue::default());
+        /// Signalled whenever tickets are taken out of the queue.
+        static ref WOKEN: Condvar = Condvar::new();
+    }
+
+    fn addr(atomic: &AtomicU32) -> usize {
+        atomic as *const AtomicU32 as usize
+    }
+
+    /// Blocks while `atomic` holds `value`.
+    ///
+    /// As under loom, the comparison and the enqueue happen atomically with
+    /// respect to the wake functions, so no wake-up is lost. Unlike loom's
+    /// model it never returns spuriously.
+    pub fn wait(atomic: &AtomicU32, value: u32) {
+        let mut queue = QUEUE.lock().unwrap();
+        if atomic.load(Relaxed) != value {
+            return;
+        }
+        let ticket = queue.next_ticket;
+        queue.next_ticket += 1;
+        queue.waiters.push((addr(atomic), ticket));
+
+        while queue.waiters.iter().any(|&(_, t)| t == ticket) {
+            queue = WOKEN.wait(queue).unwrap();
+        }
+    }
+
+    /// Wakes at most one thread blocked in [`wait`] on `atomic`.
+    pub fn wake_one(atomic: &AtomicU32) {
+        let mut queue = QUEUE.lock().unwrap();
+        let addr = addr(atomic);
+        if let Some(index) = queue.waiters.iter().position(|&(a, _)| a == addr) {
+            queue.waiters.remove(index);
+            WOKEN.notify_all();
+        }
+    }
+
+    /// Wakes every thread blocked in [`wait`] on `atomic`.
+    pub fn wake_all(atomic: &AtomicU32) {
+        let mut queue = QUEUE.lock().unwrap();
+        let addr = addr(atomic);
+        let before = queue.waiters.len();
+        queue.waiters.retain(|&(a, _)| a != addr);
+        if queue.waiters.len() != before {
+            WOKEN.notify_all();
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Probabilistic concurrency testing (PCT) on real threads, for std mode.
+//!
+//! Without a model checker the OS decides the interleaving, and a test body
+//! run in a loop tends to see the same few every time. A PCT run perturbs
+//! that: every thread taking part gets a random priority, and at every
+//! atomic operation a thread sleeps briefly while a live thread with a higher
+//! priority exists, so higher priority threads tend to run ahead. At
+//! `depth - 1` randomly chosen steps of each iteration the running thread
+//! drops to a priority below every other, which is what lets a bug that
+//! needs a few events in a particular order show up.
+//!
+//! Real threads can't be stopped outright, so this only skews the schedule
+//! rather than dictating it, but it reaches interleavings a plain loop
+//! practically never does. Only the thread running the body and threads it
+//! spawns through [`crate::sync::thread::spawn`] are perturbed, so tests
+//! running alongside are left alone. Every atomic in the run is accessed
+//! `Relaxed`, so the perturbation itself adds no synchronization.
+
+use std::cell::Cell;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering::Relaxed};
+use std::time::Duration;
+
+use crate::rng::{self, Rng};
+
+/// Threads beyond this many in one iteration run unperturbed.
+const MAX_THREADS: usize = 64;
+
+/// The largest supported depth.
+pub const MAX_DEPTH: usize = 16;
+
+/// How long a thread backs off per live thread with a higher priority.
+const BACKOFF: Duration = Duration::from_micros(20);
+
+/// The depth of the run in progress.
+static DEPTH: AtomicUsize = AtomicUsize::new(0);
+/// The iteration in progress, `0` when no run is.
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+static STEP: AtomicUsize = AtomicUsize::new(0);
+static THREADS: AtomicUsize = AtomicUsize::new(0);
+/// The seed of the iteration in progress.
+static SEED: AtomicU64 = AtomicU64::new(0);
+/// The step each change point falls on, `0` for none.
+static CHANGE_POINTS: [AtomicUsize; MAX_DEPTH] = [const { AtomicUsize::new(0) }; MAX_DEPTH];
+/// The priority of each thread in the iteration, `0` once it has exited.
+static PRIORITIES: [AtomicU64; MAX_THREADS] = [const { AtomicU64::new(0) }; MAX_THREADS];
+
+/// Only one run at a time can own the statics above.
+static RUN: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    /// The iteration this thread takes part in, and its priority slot.
+    static MEMBER: Member = const { Member { generation: Cell::new(0), slot: Cell::new(None) } };
+}
+
+struct Member {
+    generation: Cell<usize>,
+    slot: Cell<Option<usize>>,
+}
+
+impl Drop for Member {
+    fn drop(&mut self) {
+        // An exited thread no longer holds anyone back
+        if let Some(slot) = self.slot.get()
+            && self.generation.get() == GENERATION.load(Relaxed)
+        {
+            PRIORITIES[slot].store(0, Relaxed);
+        }
+    }
+}
+
+/// Runs `f` `iterations` times, perturbing the threads of each iteration with
+/// PCT of the given `depth`. Every random decision comes from `seed`, and
+/// each iteration runs with a [seed](crate::rng) of its own drawn from it.
+pub(crate) fn run(depth: usize, iterations: usize, seed: u64, f: impl Fn()) {
+    assert!(
+        (1..=MAX_DEPTH).contains(&depth),
+        "PCT depth must be between 1 and {MAX_DEPTH}, not {depth}"
+    );
+    let _run = RUN.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let _end = End;
+
+    eprintln!("PCT: seed {seed}, depth {depth}, {iterations} iterations");
+    DEPTH.store(depth, Relaxed);
+    let mut seeds = Rng::new(seed);
+
+    // Change points are spread over the length of the longest iteration so
+    // far; the first iteration only measures it
+    let mut steps = 0;
+    for _ in 0..iterations {
+        let generation = GENERATION.load(Relaxed) + 1;
+        let seed = seeds.next_u64();
+        let mut random = Rng::new(seed);
+        SEED.store(seed, Relaxed);
+        STEP.store(0, Relaxed);
+        THREADS.store(0, Relaxed);
+        for (i, point) in CHANGE_POINTS.iter().enumerate() {
+            let step = if i + 1 < depth && steps > 0 {
+                1 + random.below(steps as u64) as usize
+            } else {
+                0
+            };
+            point.store(step, Relaxed);
+        }
+        GENERATION.store(generation, Relaxed);
+        // The running thread takes the first slot, so threads get the same
+        // slots, and priorities, whenever they're spawned in the same order
+        join(Ticket {
+            generation,
+            slot: register(),
+        });
+        rng::join(Some(seed));
+
+        f();
+
+        steps = steps.max(STEP.load(Relaxed));
+    }
+}
+
+/// Ends the run, even if the body panicked, so stray threads stop backing
+/// off.
+struct End;
+
+impl Drop for End {
+    fn drop(&mut self) {
+        GENERATION.store(0, Relaxed);
+        join(Ticket::NONE);
+    }
+}
+
+/// A thread's place in an iteration, handed from a thread to one it spawns.
+pub(crate) struct Ticket {
+    generation: usize,
+    slot: Option<usize>,
+}
+
+impl Ticket {
+    /// Takes part in no iteration.
+    const NONE: Ticket = Ticket {
+        generation: 0,
+        slot: None,
+    };
+}
+
+/// A ticket for a thread about to be spawned by the current one. The new
+/// thread gets its priority right away, so the threads already running back
+/// off for it even before it has started.
+pub(crate) fn ticket() -> Ticket {
+    let generation = MEMBER.with(|member| member.generation.get());
+    if generation == 0 || generation != GENERATION.load(Relaxed) {
+        return Ticket::NONE;
+    }
+    match register() {
+        Some(slot) => Ticket {
+            generation,
+            slot: Some(slot),
+        },
+        None => Ticket::NONE,
+    }
+}
+
+/// Makes the current thread take part in the iteration of `ticket`.
+pub(crate) fn join(ticket: Ticket) {
+    MEMBER.with(|member| {
+        member.generation.set(ticket.generation);
+        member.slot.set(ticket.slot);
+    });
+}
+
+/// Called before every atomic operation. Outside a run this is a thread-local
+/// read.
+pub(crate) fn schedule_point() {
+    let Some(slot) = MEMBER.with(slot) else {
+        return;
+    };
+
+    let step = STEP.fetch_add(1, Relaxed) + 1;
+    let depth = DEPTH.load(Relaxed);
+    for (i, point) in CHANGE_POINTS[..depth].iter().enumerate() {
+        if point.load(Relaxed) == step {
+            // The i-th change point gets the i-th lowest priority
+            PRIORITIES[slot].store(i as u64 + 1, Relaxed);
+        }
+    }
+
+    let mine = PRIORITIES[slot].load(Relaxed);
+    let threads = THREADS.load(Relaxed).min(MAX_THREADS);
+    let higher = (0..threads)
+        .filter(|&other| other != slot && PRIORITIES[other].load(Relaxed) > mine)
+        .count();
+    if higher > 0 {
+        std::thread::sleep(BACKOFF * higher as u32);
+    }
+}
+
+/// The current thread's priority slot in the iteration in progress, taking
+/// one on its first step if it wasn't handed one.
+fn slot(member: &Member) -> Option<usize> {
+    let generation = member.generation.get();
+    if generation == 0 || generation != GENERATION.load(Relaxed) {
+        return None;
+    }
+    if let Some(slot) = member.slot.get() {
+        return Some(slot);
+    }
+
+    let slot = register();
+    match slot {
+        Some(_) => member.slot.set(slot),
+        None => member.generation.set(0),
+    }
+    slot
+}
+
+/// Takes a priority slot in the iteration in progress, if one is left.
+fn register() -> Option<usize> {
+    let slot = THREADS.fetch_add(1, Relaxed);
+    if slot >= MAX_THREADS {
+        return None;
+    }
+    // Initial priorities are all above the ones change points hand out
+    let random = rng::mix(SEED.load(Relaxed) ^ rng::mix(slot as u64));
+    let priority = MAX_DEPTH as u64 + 1 + random % (u64::MAX / 2);
+    PRIORITIES[slot].store(priority, Relaxed);
+    Some(slot)
+}
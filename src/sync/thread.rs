@@ -0,0 +1,26 @@
+//! `std::thread`, with a `spawn` that brings the new thread into the
+//! spawning thread's [PCT](super::pct), [seeded](crate::rng) and
+//! [watched](super::watchdog) runs.
+
+pub use std::thread::*;
+
+use super::{pct, watchdog};
+use crate::rng;
+
+/// [`std::thread::spawn`], with the new thread perturbed, seeded and watched
+/// like its parent.
+pub fn spawn<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let ticket = pct::ticket();
+    let seed = rng::current();
+    let run = watchdog::current();
+    std::thread::spawn(move || {
+        pct::join(ticket);
+        rng::join(seed);
+        watchdog::join(run);
+        f()
+    })
+}
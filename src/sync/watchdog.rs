@@ -0,0 +1,207 @@
+//! A deadlock watchdog for std mode.
+//!
+//! Under loom and shuttle a deadlock fails the test, but on real threads it
+//! just hangs. [`run`] runs a std-mode body on the calling thread while a
+//! thread of its own waits for it to finish an iteration at a time; if one
+//! takes longer than the timeout, it prints a list of the run's threads and
+//! where each one is blocked, and exits the process.
+//!
+//! Where a thread is blocked comes from the wait points of the crate's own
+//! primitives: the locks and `SafeChannel::receive` mark the thread as
+//! blocked, with the caller's location, for as long as they wait. The
+//! threads of a run are the one running the body and those it spawns
+//! through [`crate::sync::thread::spawn`]; a thread blocked anywhere else,
+//! such as in a `join`, is listed as not at a wait point.
+//!
+//! The blocked threads can't be stopped, and the body borrows from the
+//! test's own thread, so exiting is the only way to fail the test. That
+//! takes the rest of the test binary with it, but so would the hang.
+
+use std::cell::Cell;
+use std::fmt::Write;
+use std::io::{self, Write as _};
+use std::panic::{self, AssertUnwindSafe, Location};
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread::ThreadId;
+use std::time::{Duration, Instant};
+
+/// The last run handed out, `0` standing for none.
+static RUNS: AtomicU64 = AtomicU64::new(0);
+
+/// Every thread taking part in a run, in the order they joined.
+static THREADS: Mutex<Vec<Member>> = Mutex::new(Vec::new());
+
+thread_local! {
+    /// The run this thread takes part in, and its id once it has joined.
+    static CURRENT: Current = const { Current { run: Cell::new(0), id: Cell::new(None) } };
+}
+
+struct Current {
+    run: Cell<u64>,
+    id: Cell<Option<ThreadId>>,
+}
+
+impl Drop for Current {
+    fn drop(&mut self) {
+        // `thread::current` may be gone by now, hence the saved id
+        if let Some(id) = self.id.get() {
+            threads().retain(|member| member.id != id);
+        }
+    }
+}
+
+struct Member {
+    id: ThreadId,
+    run: u64,
+    name: String,
+    waiting: Option<Wait>,
+}
+
+struct Wait {
+    what: &'static str,
+    location: &'static Location<'static>,
+    since: Instant,
+}
+
+fn threads() -> MutexGuard<'static, Vec<Member>> {
+    THREADS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Runs `body`, passing it a function to call after each iteration, and
+/// exits the process with a report of where the run's threads are if an
+/// iteration doesn't finish within `timeout`.
+pub(crate) fn run<R>(timeout: Duration, body: impl FnOnce(&dyn Fn()) -> R) -> R {
+    let run = RUNS.fetch_add(1, Relaxed) + 1;
+    let (progress, iterations) = mpsc::channel();
+    let seed = crate::rng::current();
+
+    let monitor = std::thread::Builder::new()
+        .name("deadlock watchdog".to_string())
+        .spawn(move || {
+            let mut iteration = 1;
+            loop {
+                match iterations.recv_timeout(timeout) {
+                    Ok(()) => iteration += 1,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                    Err(RecvTimeoutError::Timeout) => deadlocked(run, iteration, timeout, seed),
+                }
+            }
+        })
+        .expect("failed to spawn the deadlock watchdog");
+
+    join(run);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        body(&|| {
+            let _ = progress.send(());
+        })
+    }));
+    leave();
+
+    drop(progress);
+    let _ = monitor.join();
+    result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+/// Prints the report of a run that's stuck in `iteration`, then exits.
+fn deadlocked(run: u64, iteration: usize, timeout: Duration, seed: Option<u64>) -> ! {
+    let mut report = report(run, iteration, timeout);
+    if let Some(seed) = seed {
+        let _ = write!(
+            report,
+            "\nfailed with seed {seed}; rerun with {}={seed} to replay its random decisions",
+            crate::rng::SEED_VAR
+        );
+    }
+    // Straight to stderr, as the test harness's capture is never printed
+    // once the process exits
+    let _ = writeln!(io::stderr().lock(), "{report}");
+    std::process::exit(101);
+}
+
+/// The run the current thread takes part in, `0` for none.
+pub(crate) fn current() -> u64 {
+    CURRENT.with(|current| current.run.get())
+}
+
+/// Makes the current thread take part in `run`, if it's not `0`.
+pub(crate) fn join(run: u64) {
+    if run == 0 {
+        return;
+    }
+    let thread = std::thread::current();
+    CURRENT.with(|current| {
+        current.run.set(run);
+        current.id.set(Some(thread.id()));
+    });
+    threads().push(Member {
+        id: thread.id(),
+        run,
+        name: thread.name().unwrap_or("<unnamed>").to_string(),
+        waiting: None,
+    });
+}
+
+/// Stops the current thread taking part in its run.
+fn leave() {
+    if let Some(id) = CURRENT.with(|current| current.id.take()) {
+        threads().retain(|member| member.id != id);
+    }
+    CURRENT.with(|current| current.run.set(0));
+}
+
+/// Marks the current thread as blocked in `what`, called from `location`,
+/// until the returned guard is dropped. Outside a run this is a
+/// thread-local read.
+pub(crate) fn wait_point(what: &'static str, location: &'static Location<'static>) -> WaitPoint {
+    let Some(id) = CURRENT.with(|current| current.id.get()) else {
+        return WaitPoint { id: None };
+    };
+    if let Some(member) = threads().iter_mut().find(|member| member.id == id) {
+        member.waiting = Some(Wait {
+            what,
+            location,
+            since: Instant::now(),
+        });
+    }
+    WaitPoint { id: Some(id) }
+}
+
+/// The current thread is blocked until this is dropped.
+pub(crate) struct WaitPoint {
+    id: Option<ThreadId>,
+}
+
+impl Drop for WaitPoint {
+    fn drop(&mut self) {
+        let Some(id) = self.id else { return };
+        if let Some(member) = threads().iter_mut().find(|member| member.id == id) {
+            member.waiting = None;
+        }
+    }
+}
+
+fn report(run: u64, iteration: usize, timeout: Duration) -> String {
+    let mut report = format!(
+        "deadlock watchdog: iteration {iteration} didn't finish within {timeout:?}; \
+         the threads of the run are:"
+    );
+    for member in threads().iter().filter(|member| member.run == run) {
+        let _ = write!(report, "\n  thread '{}' ({:?}): ", member.name, member.id);
+        let _ = match &member.waiting {
+            Some(wait) => write!(
+                report,
+                "blocked in {} at {} for {:.2?}",
+                wait.what,
+                wait.location,
+                wait.since.elapsed()
+            ),
+            None => write!(
+                report,
+                "running, or blocked outside the crate's wait points"
+            ),
+        };
+    }
+    report
+}
@@ -0,0 +1,644 @@
+//! Turns the `LOOM_LOG=trace` output of a test run into something a person
+//! can follow.
+//!
+//! The raw log has a line for every branch point, `Arc` reference count and
+//! internal synchronisation step. This module keeps only the operations that
+//! matter for understanding an interleaving (atomic accesses, `UnsafeCell`
+//! borrows through [`crate::sync::DerefExt`], lock acquisitions, waits and
+//! wake-ups, spawns, yields and thread exits),
+//! attributes each one to the thread that performed it, and lays them out in
+//! one column per thread. The panic that ended an execution, if any, is kept
+//! along with the step it happened after.
+//!
+//! `loom-kit trace` saves the log to `loom_test_artifacts/<test>.log` and
+//! `loom-kit report` prints the timeline of its failing execution. Colour
+//! codes are stripped, so logs captured from a terminal parse too.
+
+use std::fmt;
+
+/// One execution (iteration) of the model, as found in the log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Execution {
+    /// Loom's iteration number, starting at 1.
+    pub iteration: usize,
+    /// The operations performed, in the order they happened.
+    pub events: Vec<Event>,
+    /// The panic that ended this execution, if it failed.
+    pub panic: Option<Panic>,
+}
+
+/// A single operation by a single thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// Loom's id for the thread; 0 is the thread the model started on.
+    pub thread: usize,
+    pub op: Op,
+    /// Where the operation was called from, when `LOOM_LOCATION` recorded
+    /// it.
+    pub location: Option<String>,
+}
+
+/// The operations shown in a timeline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    Load {
+        object: Object,
+        ordering: String,
+    },
+    Store {
+        object: Object,
+        ordering: String,
+    },
+    /// A read-modify-write. `stored` is whether it stored, as traced by
+    /// [`crate::sync::atomic`]: a failed compare-exchange only reads. It's
+    /// `None` if the log doesn't say, e.g. for loom's atomics used directly.
+    Rmw {
+        object: Object,
+        ordering: String,
+        stored: Option<bool>,
+    },
+    /// Borrowing an `UnsafeCell`'s contents through
+    /// [`crate::sync::DerefExt::get_ext`].
+    Read {
+        object: Object,
+    },
+    /// Borrowing an `UnsafeCell`'s contents mutably through
+    /// [`crate::sync::DerefExt::get_mut_ext`].
+    Write {
+        object: Object,
+    },
+    /// Acquiring a `Mutex`, or either side of an `RwLock`. `contended` is
+    /// set if it was held by another thread at the time.
+    Lock {
+        object: Object,
+        mode: LockMode,
+        contended: bool,
+    },
+    Wait {
+        object: Object,
+    },
+    Notify {
+        object: Object,
+    },
+    Spawn {
+        thread: usize,
+    },
+    Yield,
+    Park,
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    Exclusive,
+    Read,
+    Write,
+}
+
+/// An object loom tracks, e.g. `atomic#2`. Ids are shared by every kind
+/// of object and count up from 0 in creation order within an execution.
+/// Cells, e.g. `cell#0`, are the exception: loom doesn't log them, so they
+/// are numbered separately, in the order they were first borrowed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Object {
+    pub kind: String,
+    pub id: usize,
+}
+
+/// A panic, e.g. a failed assertion, that ended an execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Panic {
+    /// The thread that was running when it panicked.
+    pub thread: Option<usize>,
+    /// `file:line:column` of the panic.
+    pub location: String,
+    pub message: String,
+    /// The number of events that happened before the panic.
+    pub after: usize,
+}
+
+/// Parses every execution out of a trace log.
+pub fn parse(log: &str) -> Vec<Execution> {
+    let mut parser = Parser::default();
+    let mut lines = log.lines().map(strip_ansi).peekable();
+
+    while let Some(line) = lines.next() {
+        if let Some(location) = panic_location(&line) {
+            let mut message = Vec::new();
+            while let Some(next) = lines.next_if(|next| !ends_panic_message(next)) {
+                message.push(next);
+            }
+            parser.panic(location, message.join("\n"));
+        } else {
+            parser.line(&line);
+        }
+    }
+
+    parser.finish()
+}
+
+/// The execution a report should show: the last one that panicked, or the
+/// last one in the log if none did.
+pub fn failing(executions: &[Execution]) -> Option<&Execution> {
+    executions
+        .iter()
+        .rev()
+        .find(|execution| execution.panic.is_some())
+        .or(executions.last())
+}
+
+impl Execution {
+    /// The ids of every thread that did something, in order.
+    pub fn threads(&self) -> Vec<usize> {
+        let mut threads: Vec<usize> = self.events.iter().map(|event| event.thread).collect();
+        threads.sort_unstable();
+        threads.dedup();
+        threads
+    }
+
+    /// A table with a row per event and a column per thread.
+    pub fn timeline(&self) -> Timeline<'_> {
+        Timeline(self)
+    }
+}
+
+impl Op {
+    /// The object the operation touched, if any.
+    pub fn object(&self) -> Option<&Object> {
+        match self {
+            Op::Load { object, .. }
+            | Op::Store { object, .. }
+            | Op::Rmw { object, .. }
+            | Op::Read { object }
+            | Op::Write { object }
+            | Op::Lock { object, .. }
+            | Op::Wait { object }
+            | Op::Notify { object } => Some(object),
+            Op::Spawn { .. } | Op::Yield | Op::Park | Op::Done => None,
+        }
+    }
+}
+
+/// The per-thread column view of an [`Execution`]. The failing step is
+/// marked with `>>` and followed by the panic message.
+pub struct Timeline<'a>(&'a Execution);
+
+impl fmt::Display for Timeline<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let execution = self.0;
+        let threads = execution.threads();
+        let cells: Vec<String> = execution.events.iter().map(Event::to_string).collect();
+
+        let widths: Vec<usize> = threads
+            .iter()
+            .map(|&thread| {
+                execution
+                    .events
+                    .iter()
+                    .zip(&cells)
+                    .filter(|(event, _)| event.thread == thread)
+                    .map(|(_, cell)| cell.len())
+                    .chain([format!("thread {thread}").len()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        // The step after which the model panicked
+        let failed = execution
+            .panic
+            .as_ref()
+            .and_then(|panic| panic.after.checked_sub(1));
+
+        writeln!(f, "iteration {}", execution.iteration)?;
+        let header: Vec<String> = threads
+            .iter()
+            .map(|thread| format!("thread {thread}"))
+            .collect();
+        let header: Vec<&str> = header.iter().map(String::as_str).collect();
+        write_row(f, "   step", &header, &widths)?;
+
+        for (step, (event, text)) in execution.events.iter().zip(&cells).enumerate() {
+            let marker = if failed == Some(step) { ">>" } else { "  " };
+            let row: Vec<&str> = threads
+                .iter()
+                .map(|&thread| if event.thread == thread { text } else { "" })
+                .collect();
+            write_row(f, &format!("{marker}{step:>5}"), &row, &widths)?;
+        }
+
+        if let Some(panic) = &execution.panic {
+            writeln!(f)?;
+            match panic.thread {
+                Some(thread) => write!(f, "thread {thread} panicked")?,
+                None => write!(f, "panicked")?,
+            }
+            writeln!(f, " at {}:", panic.location)?;
+            for line in panic.message.lines() {
+                writeln!(f, "  {line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes `first` and then `cells` padded to `widths`, without trailing
+/// spaces.
+fn write_row(
+    f: &mut fmt::Formatter<'_>,
+    first: &str,
+    cells: &[&str],
+    widths: &[usize],
+) -> fmt::Result {
+    let mut row = first.to_string();
+    for (cell, width) in cells.iter().zip(widths) {
+        row += &format!(" | {cell:width$}");
+    }
+    writeln!(f, "{}", row.trim_end())
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.op)?;
+        if let Some(location) = &self.location {
+            write!(f, " @ {location}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::Load { object, ordering } => write!(f, "load {object} {ordering}"),
+            Op::Store { object, ordering } => write!(f, "store {object} {ordering}"),
+            Op::Rmw {
+                object,
+                ordering,
+                stored,
+            } => {
+                write!(f, "rmw {object} {ordering}")?;
+                if *stored == Some(false) {
+                    write!(f, " (failed)")?;
+                }
+                Ok(())
+            }
+            Op::Read { object } => write!(f, "read {object}"),
+            Op::Write { object } => write!(f, "write {object}"),
+            Op::Lock {
+                object,
+                mode,
+                contended,
+            } => {
+                match mode {
+                    LockMode::Exclusive => write!(f, "lock {object}")?,
+                    LockMode::Read => write!(f, "read-lock {object}")?,
+                    LockMode::Write => write!(f, "write-lock {object}")?,
+                }
+                if *contended {
+                    write!(f, " (contended)")?;
+                }
+                Ok(())
+            }
+            Op::Wait { object } => write!(f, "wait {object}"),
+            Op::Notify { object } => write!(f, "notify {object}"),
+            Op::Spawn { thread } => write!(f, "spawn thread {thread}"),
+            Op::Yield => write!(f, "yield"),
+            Op::Park => write!(f, "park"),
+            Op::Done => write!(f, "done"),
+        }
+    }
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.kind, self.id)
+    }
+}
+
+#[derive(Default)]
+struct Parser {
+    executions: Vec<Execution>,
+    current: Option<Execution>,
+    /// The thread named by the most recent line.
+    thread: Option<usize>,
+    /// A lock a thread is about to take. Loom logs the attempt before it
+    /// may switch threads, so it is only recorded once that thread runs
+    /// again and the lock is really held.
+    pending_lock: Option<Event>,
+    /// The address of every cell borrowed in the current execution, in the
+    /// order they were first borrowed.
+    cells: Vec<String>,
+}
+
+impl Parser {
+    fn line(&mut self, line: &str) {
+        if let Some(iteration) = iteration_header(line) {
+            self.start(iteration);
+            return;
+        }
+
+        let Some((spans, message)) = split_log_line(line) else {
+            return;
+        };
+        let Some(thread) = span_field(spans, "thread{id=") else {
+            return;
+        };
+        self.thread = Some(thread);
+
+        if !message.starts_with("branch ")
+            && let Some(lock) = self.pending_lock.take_if(|lock| lock.thread == thread)
+        {
+            self.push(lock);
+        }
+
+        if message.starts_with("Atomic::rmw done ") {
+            self.rmw_done(thread, field(message, "stored") == Some("true"));
+            return;
+        }
+
+        let location = field(message, "location").map(str::to_string);
+        let event = |op| Event {
+            thread,
+            op,
+            location: location.clone(),
+        };
+
+        if let Some((address, write)) = cell_access(message) {
+            let object = self.cell(address);
+            self.push(event(if write {
+                Op::Write { object }
+            } else {
+                Op::Read { object }
+            }));
+        } else if let Some(op) = operation(message) {
+            self.push(event(op));
+        } else if let Some((object, mode, contended)) = lock_attempt(message) {
+            self.pending_lock = Some(event(Op::Lock {
+                object,
+                mode,
+                contended,
+            }));
+        }
+    }
+
+    fn panic(&mut self, location: String, message: String) {
+        let thread = self.thread;
+        let Some(execution) = &mut self.current else {
+            return;
+        };
+        // Unwinding can panic again in drop code; the first one is the bug
+        if execution.panic.is_none() {
+            execution.panic = Some(Panic {
+                thread,
+                location,
+                message,
+                after: execution.events.len(),
+            });
+        }
+    }
+
+    /// Records whether the thread's last operation, a read-modify-write,
+    /// stored.
+    fn rmw_done(&mut self, thread: usize, done: bool) {
+        let last = self.current.as_mut().and_then(|execution| {
+            execution
+                .events
+                .iter_mut()
+                .rev()
+                .find(|event| event.thread == thread)
+        });
+        if let Some(Event {
+            op: Op::Rmw { stored, .. },
+            ..
+        }) = last
+        {
+            stored.get_or_insert(done);
+        }
+    }
+
+    /// The cell at `address`, numbered by when it was first borrowed.
+    fn cell(&mut self, address: &str) -> Object {
+        let id = match self.cells.iter().position(|cell| cell == address) {
+            Some(id) => id,
+            None => {
+                self.cells.push(address.to_string());
+                self.cells.len() - 1
+            }
+        };
+        Object {
+            kind: "cell".to_string(),
+            id,
+        }
+    }
+
+    fn push(&mut self, event: Event) {
+        self.current
+            .get_or_insert_with(|| Execution {
+                iteration: 0,
+                events: Vec::new(),
+                panic: None,
+            })
+            .events
+            .push(event);
+    }
+
+    fn start(&mut self, iteration: usize) {
+        self.end();
+        self.current = Some(Execution {
+            iteration,
+            events: Vec::new(),
+            panic: None,
+        });
+    }
+
+    fn end(&mut self) {
+        if let Some(lock) = self.pending_lock.take() {
+            self.push(lock);
+        }
+        self.thread = None;
+        self.cells.clear();
+        self.executions.extend(self.current.take());
+    }
+
+    fn finish(mut self) -> Vec<Execution> {
+        self.end();
+        self.executions
+    }
+}
+
+/// The operation logged by `message`, if it is one a timeline shows.
+fn operation(message: &str) -> Option<Op> {
+    let object = || field(message, "state").and_then(parse_object);
+    let ordering = || field(message, "ordering").unwrap_or("").to_string();
+
+    Some(if message.starts_with("Atomic::load ") {
+        Op::Load {
+            object: object()?,
+            ordering: ordering(),
+        }
+    } else if message.starts_with("Atomic::unsync_load ") {
+        Op::Load {
+            object: object()?,
+            ordering: "unsync".to_string(),
+        }
+    } else if message.starts_with("Atomic::store ") {
+        Op::Store {
+            object: object()?,
+            ordering: ordering(),
+        }
+    } else if message.starts_with("Atomic::rmw ") {
+        Op::Rmw {
+            object: object()?,
+            ordering: format!(
+                "{}/{}",
+                field(message, "success").unwrap_or(""),
+                field(message, "failure").unwrap_or("")
+            ),
+            stored: None,
+        }
+    } else if message.starts_with("Notify::wait 1 ") || message.starts_with("Condvar::wait ") {
+        Op::Wait { object: object()? }
+    } else if message.starts_with("Notify::notify ")
+        || message.starts_with("Condvar::notify_one ")
+        || message.starts_with("Condvar::notify_all ")
+    {
+        Op::Notify { object: object()? }
+    } else if message.starts_with("spawn ") {
+        Op::Spawn {
+            thread: parse_thread_id(field(message, "thread")?)?,
+        }
+    } else if message.starts_with("yield_now ") {
+        Op::Yield
+    } else if message.starts_with("park ") {
+        Op::Park
+    } else if message.starts_with("thread_done: drop locals ") {
+        Op::Done
+    } else {
+        return None;
+    })
+}
+
+/// The address of the cell a [`crate::sync::DerefExt`] borrow logged by
+/// `message` was of, and whether the borrow was mutable.
+fn cell_access(message: &str) -> Option<(&str, bool)> {
+    let write = if message.starts_with("UnsafeCell::with ") {
+        false
+    } else if message.starts_with("UnsafeCell::with_mut ") {
+        true
+    } else {
+        return None;
+    };
+    Some((field(message, "cell")?, write))
+}
+
+/// A lock acquisition, logged as a branch on a mutex or rwlock.
+fn lock_attempt(message: &str) -> Option<(Object, LockMode, bool)> {
+    let object = parse_object(field(message, "obj")?)?;
+    let is = |value| field(message, "is_locked") == Some(value);
+
+    if message.starts_with("Object::branch_acquire ") && object.kind == "mutex" {
+        Some((object, LockMode::Exclusive, is("true")))
+    } else if message.starts_with("Object::branch_disable ") && object.kind == "rwlock" {
+        let mode = match field(message, "action")? {
+            "Read" => LockMode::Read,
+            "Write" => LockMode::Write,
+            _ => return None,
+        };
+        Some((object, mode, field(message, "disable") == Some("true")))
+    } else {
+        None
+    }
+}
+
+/// `Ref<loom::rt::atomic::State>(2)` is `atomic#2`.
+fn parse_object(value: &str) -> Option<Object> {
+    let (path, id) = value.strip_prefix("Ref<")?.split_once(">(")?;
+    let kind = path.strip_suffix("::State")?.rsplit("::").next()?;
+    Some(Object {
+        kind: kind.to_string(),
+        id: id.strip_suffix(')')?.parse().ok()?,
+    })
+}
+
+/// `Id(1)` is thread 1.
+fn parse_thread_id(value: &str) -> Option<usize> {
+    value.strip_prefix("Id(")?.strip_suffix(')')?.parse().ok()
+}
+
+/// The value of `name=value` in a log message. Values never contain spaces.
+fn field<'a>(message: &'a str, name: &str) -> Option<&'a str> {
+    message.split(' ').find_map(|word| {
+        word.strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix('='))
+    })
+}
+
+/// The number following `prefix` in the span list, e.g. `thread{id=` in
+/// `iter{3}:thread{id=1}`.
+fn span_field(spans: &str, prefix: &str) -> Option<usize> {
+    let start = spans.find(prefix)? + prefix.len();
+    let rest = &spans[start..];
+    rest[..rest.find('}')?].parse().ok()
+}
+
+/// Splits `TRACE iter{1}:thread{id=0}: loom::rt::atomic: Atomic::load ...`
+/// into its spans and its message. Lines traced by the crate itself, under
+/// `rust_atomics::`, are split the same way.
+fn split_log_line(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start();
+    let rest = ["TRACE ", "DEBUG ", "INFO ", "WARN ", "ERROR "]
+        .iter()
+        .find_map(|level| line.strip_prefix(level))?;
+    let target = ["loom::", "rust_atomics::"]
+        .iter()
+        .filter_map(|target| rest.find(target))
+        .min()?;
+    let (spans, rest) = rest.split_at(target);
+    let (_, message) = rest.split_once(": ")?;
+    Some((spans, message))
+}
+
+/// `================== Iteration 23 ==================`
+fn iteration_header(line: &str) -> Option<usize> {
+    let (_, rest) = line.split_once("= Iteration ")?;
+    rest.split_whitespace().next()?.parse().ok()
+}
+
+/// `thread 'name' (1234) panicked at tests/loom.rs:139:9:`
+fn panic_location(line: &str) -> Option<String> {
+    if !line.starts_with("thread '") {
+        return None;
+    }
+    let (_, location) = line.split_once(" panicked at ")?;
+    Some(location.strip_suffix(':').unwrap_or(location).to_string())
+}
+
+/// Whether `line` comes after a panic message rather than being part of it.
+fn ends_panic_message(line: &str) -> bool {
+    line.is_empty()
+        || split_log_line(line).is_some()
+        || line.starts_with("stack backtrace:")
+        || line.starts_with("note: ")
+        || line.starts_with("test ")
+        || line.starts_with("thread '")
+}
+
+/// Removes terminal colour codes such as `\x1b[35m`.
+fn strip_ansi(line: &str) -> String {
+    let mut plain = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip the parameters up to the final letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
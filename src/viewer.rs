@@ -0,0 +1,202 @@
+//! A single-file HTML page for stepping through one loom execution.
+//!
+//! The page shows the execution's [timeline](crate::trace::Timeline) with a
+//! slider to scrub through it one operation at a time. At each step it
+//! highlights the thread that ran and what it did, and lists every object
+//! touched so far with the last operation on it. It has no external
+//! dependencies, so it can be attached to a review or opened offline.
+//!
+//! Besides loom's atomics, locks and notifications, the objects include
+//! every `UnsafeCell` borrowed through [`crate::sync::DerefExt`], as
+//! `cell#N`.
+
+use serde_json::json;
+
+use crate::checkpoint::Checkpoint;
+use crate::trace::Execution;
+
+/// Renders `execution` of `test` as a self-contained HTML page.
+/// `checkpoint` adds a summary of the explored path to the header.
+pub fn render(test: &str, execution: &Execution, checkpoint: Option<&Checkpoint>) -> String {
+    let failed = execution
+        .panic
+        .as_ref()
+        .and_then(|panic| panic.after.checked_sub(1));
+    let steps: Vec<_> = execution
+        .events
+        .iter()
+        .enumerate()
+        .map(|(step, event)| {
+            json!({
+                "thread": event.thread,
+                "op": event.to_string(),
+                "object": event.op.object().map(ToString::to_string),
+                "failed": failed == Some(step),
+            })
+        })
+        .collect();
+
+    let summary = checkpoint.map(|checkpoint| {
+        format!(
+            "Iteration {}: {} branch points, {} preemptions.",
+            execution.iteration,
+            checkpoint.branch_count(),
+            checkpoint.preemptions().len()
+        )
+    });
+    let panic = execution.panic.as_ref().map(|panic| {
+        let thread = panic
+            .thread
+            .map_or_else(String::new, |thread| format!("thread {thread} "));
+        format!("{thread}panicked at {}:\n{}", panic.location, panic.message)
+    });
+
+    let data = json!({
+        "threads": execution.threads(),
+        "steps": steps,
+        "summary": summary.unwrap_or_else(|| format!("Iteration {}.", execution.iteration)),
+        "panic": panic,
+    });
+    // Keep `</script>` in a panic message from ending the script early
+    let data = data.to_string().replace("</", "<\\/");
+
+    TEMPLATE
+        .replace("{{test}}", &escape(test))
+        .replace("{{data}}", &data)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{{test}} - loom execution</title>
+<style>
+  body { font-family: sans-serif; margin: 1.5em; color: #222; }
+  h1 { font-size: 1.4em; }
+  h2 { font-size: 1.1em; margin-top: 0; }
+  #controls { margin: 1em 0; display: flex; gap: 0.5em; align-items: center; }
+  #step { width: 30em; }
+  #current { font-family: monospace; font-size: 1.1em; min-height: 1.4em; }
+  #views { display: flex; gap: 3em; align-items: flex-start; }
+  table { border-collapse: collapse; font-family: monospace; }
+  th, td { padding: 2px 12px; text-align: left; white-space: nowrap; }
+  th { border-bottom: 1px solid #888; }
+  #timeline td + td, #timeline th + th { border-left: 1px solid #ccc; }
+  #timeline tr { cursor: pointer; }
+  tr.future { opacity: 0.25; }
+  tr.current { background: #ffe98a; }
+  td.failed { color: #c00; font-weight: bold; }
+  tr.touched { background: #ffe98a; }
+  #panic { color: #c00; white-space: pre-wrap; }
+</style>
+</head>
+<body>
+<h1>{{test}}</h1>
+<p id="summary"></p>
+<div id="controls">
+  <button id="first" title="First step (Home)">&#x23EE;</button>
+  <button id="prev" title="Previous step (&larr;)">&#x25C0;</button>
+  <input type="range" id="step" min="0" value="0">
+  <button id="next" title="Next step (&rarr;)">&#x25B6;</button>
+  <button id="last" title="Last step (End)">&#x23ED;</button>
+  <span id="position"></span>
+</div>
+<p id="current"></p>
+<div id="views">
+  <table id="timeline"></table>
+  <div>
+    <h2>Objects</h2>
+    <table id="objects"></table>
+  </div>
+</div>
+<pre id="panic"></pre>
+<script>
+const data = {{data}};
+
+const slider = document.getElementById("step");
+const timeline = document.getElementById("timeline");
+const objects = document.getElementById("objects");
+const last = Math.max(data.steps.length - 1, 0);
+slider.max = last;
+document.getElementById("summary").textContent = data.summary;
+
+function cell(tag, text) {
+  const element = document.createElement(tag);
+  element.textContent = text;
+  return element;
+}
+
+const header = document.createElement("tr");
+header.append(cell("th", "step"), ...data.threads.map(thread => cell("th", "thread " + thread)));
+timeline.append(header);
+
+const rows = data.steps.map((step, index) => {
+  const row = document.createElement("tr");
+  row.append(cell("td", index));
+  for (const thread of data.threads) {
+    const td = cell("td", thread === step.thread ? step.op : "");
+    if (thread === step.thread && step.failed) td.className = "failed";
+    row.append(td);
+  }
+  row.addEventListener("click", () => show(index));
+  timeline.append(row);
+  return row;
+});
+
+function show(index) {
+  index = Math.min(Math.max(index, 0), last);
+  slider.value = index;
+  rows.forEach((row, i) => {
+    row.className = i === index ? "current" : i > index ? "future" : "";
+  });
+
+  const step = data.steps[index];
+  document.getElementById("position").textContent =
+    data.steps.length ? "step " + index + " of " + last : "no steps";
+  document.getElementById("current").textContent =
+    step ? "thread " + step.thread + ": " + step.op : "";
+
+  // The last operation on each object up to this step
+  const touched = new Map();
+  data.steps.slice(0, index + 1).forEach((s, i) => {
+    if (s.object !== null) touched.set(s.object, [i, s]);
+  });
+  objects.replaceChildren(cell("tr", ""));
+  objects.firstChild.append(cell("th", "object"), cell("th", "last touched"));
+  for (const [object, [i, s]] of [...touched].sort(([a], [b]) => a.localeCompare(b))) {
+    const row = document.createElement("tr");
+    if (i === index) row.className = "touched";
+    row.append(cell("td", object), cell("td", "step " + i + ", thread " + s.thread + ": " + s.op));
+    objects.append(row);
+  }
+
+  const failed = data.panic !== null && step && step.failed;
+  document.getElementById("panic").textContent = failed ? data.panic : "";
+}
+
+slider.addEventListener("input", () => show(Number(slider.value)));
+document.getElementById("first").addEventListener("click", () => show(0));
+document.getElementById("prev").addEventListener("click", () => show(Number(slider.value) - 1));
+document.getElementById("next").addEventListener("click", () => show(Number(slider.value) + 1));
+document.getElementById("last").addEventListener("click", () => show(last));
+document.addEventListener("keydown", event => {
+  const moves = { ArrowLeft: -1, ArrowRight: 1 };
+  if (event.key in moves) show(Number(slider.value) + moves[event.key]);
+  else if (event.key === "Home") show(0);
+  else if (event.key === "End") show(last);
+  else return;
+  event.preventDefault();
+});
+
+show(0);
+</script>
+</body>
+</html>
+"#;
@@ -0,0 +1,149 @@
+use rust_atomics::checkpoint::{Branch, Checkpoint, Preemption, ThreadState};
+
+/// Written by `loom-kit checkpoint release_and_acquire_incorrect`.
+const RELEASE_AND_ACQUIRE: &str = include_str!("fixtures/release_and_acquire_incorrect.json");
+
+#[test]
+fn parse_saved_checkpoint() {
+    let checkpoint: Checkpoint = RELEASE_AND_ACQUIRE.parse().unwrap();
+
+    assert_eq!(checkpoint.preemption_bound, None);
+    assert_eq!(checkpoint.branch_count(), 10);
+    assert_eq!(checkpoint.schedules().count(), 9);
+    assert_eq!(checkpoint.thread_count(), 2);
+
+    // The main thread spawns thread 1, which is switched in straight away
+    // and runs to completion before main carries on
+    assert_eq!(
+        checkpoint.schedule_path(),
+        [0, 0, 1, 1, 1, 1, 1, 1, 0].map(Some)
+    );
+    assert_eq!(checkpoint.thread_at(2), Some(1));
+    assert_eq!(checkpoint.thread_at(9), None);
+
+    assert_eq!(checkpoint.preempted_at(1), None);
+    assert_eq!(checkpoint.preempted_at(2), Some(0));
+    assert_eq!(
+        checkpoint.preemptions(),
+        [Preemption {
+            step: 2,
+            preempted: 0,
+            by: 1
+        }]
+    );
+
+    // Thread 1 finishing isn't a preemption
+    assert_eq!(checkpoint.preempted_at(8), None);
+    let Branch::Schedule(last) = &checkpoint.branches[8] else {
+        panic!("expected a schedule, got {:?}", checkpoint.branches[8]);
+    };
+    assert_eq!(last.preemptions, 1);
+    assert_eq!(last.prev, Some(7));
+    assert_eq!(last.threads[1], ThreadState::Disabled);
+
+    // The final load reads the second of the two stores it could see
+    let loads: Vec<_> = checkpoint.loads().collect();
+    assert_eq!(loads.len(), 1);
+    assert_eq!(loads[0].values, [0, 1]);
+    assert_eq!(loads[0].chosen(), 1);
+}
+
+#[test]
+fn parse_spurious_wakeup() {
+    let checkpoint: Checkpoint = r#"{
+        "preemption_bound": 2,
+        "branches": {"entries": [{"Spurious": {"spur": true, "exploring": true}}]}
+    }"#
+    .parse()
+    .unwrap();
+
+    assert_eq!(checkpoint.preemption_bound, Some(2));
+    assert!(matches!(
+        checkpoint.branches[..],
+        [Branch::Spurious(ref spurious)] if spurious.spurious
+    ));
+    assert_eq!(checkpoint.thread_count(), 0);
+}
+
+#[test]
+fn reject_out_of_range_load() {
+    let error = r#"{
+        "preemption_bound": null,
+        "branches": {"entries": [
+            {"Load": {"values": [0, 1, 0, 0, 0, 0, 0], "pos": 2, "len": 2, "exploring": true}}
+        ]}
+    }"#
+    .parse::<Checkpoint>()
+    .unwrap_err();
+
+    assert!(error.to_string().contains("out of range"), "{error}");
+}
+
+#[test]
+fn display_lists_every_branch() {
+    let checkpoint: Checkpoint = RELEASE_AND_ACQUIRE.parse().unwrap();
+    let text = checkpoint.to_string();
+
+    assert!(
+        text.starts_with("10 branches, 2 threads, 1 preemptions"),
+        "{text}"
+    );
+    assert!(
+        text.contains("step   2: thread 1 (preempts thread 0)"),
+        "{text}"
+    );
+    assert!(
+        text.contains("load reads store 1 (choice 2 of 2)"),
+        "{text}"
+    );
+}
+
+#[test]
+fn progress_estimate() {
+    // The first load is on its second of two choices, so half the tree is
+    // behind it, and the second load adds half of what's left of that half
+    let checkpoint: Checkpoint = r#"{
+        "preemption_bound": null,
+        "branches": {"entries": [
+            {"Load": {"values": [0, 1, 0, 0, 0, 0, 0], "pos": 1, "len": 2, "exploring": true}},
+            {"Spurious": {"spur": false, "exploring": true}},
+            {"Load": {"values": [0, 1, 2, 0, 0, 0, 0], "pos": 2, "len": 3, "exploring": true}}
+        ]}
+    }"#
+    .parse()
+    .unwrap();
+    assert!((checkpoint.progress() - (0.5 + 0.25 * 2.0 / 3.0)).abs() < 1e-9);
+
+    let checkpoint: Checkpoint = RELEASE_AND_ACQUIRE.parse().unwrap();
+    assert!((0.0..1.0).contains(&checkpoint.progress()));
+}
+
+/// Parses a checkpoint straight from loom, so a change in its format shows
+/// up here rather than in a report.
+#[cfg(loom)]
+#[test]
+fn parse_fresh_checkpoint() {
+    use rust_atomics::model::Builder;
+    use rust_atomics::sync::atomic::{AtomicU32, Ordering::Relaxed};
+    use rust_atomics::sync::{Arc, thread};
+
+    let path = std::env::temp_dir().join(format!("loom-checkpoint-{}.json", std::process::id()));
+    Builder::new()
+        .checkpoint_file(&path)
+        .checkpoint_interval(1)
+        .check(|| {
+            let a = Arc::new(AtomicU32::new(0));
+            let a2 = a.clone();
+            let t = thread::spawn(move || a2.store(1, Relaxed));
+            a.load(Relaxed);
+            t.join().unwrap();
+        });
+
+    let checkpoint = Checkpoint::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // The file holds the prefix loom resumes the last execution from, so
+    // only its start is predictable
+    assert_eq!(checkpoint.thread_count(), 2);
+    assert_eq!(checkpoint.thread_at(0), Some(0));
+}
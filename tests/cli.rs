@@ -0,0 +1,101 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+use rust_atomics::concurrent_test;
+use rust_atomics::sync::atomic::AtomicBool;
+use rust_atomics::sync::atomic::Ordering::{Acquire, Release};
+use rust_atomics::sync::{Arc, thread};
+
+fn loom_kit(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_loom-kit"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn usage_errors() {
+    // No command
+    assert_eq!(loom_kit(&[]).status.code(), Some(64));
+
+    // Unknown command
+    assert_eq!(loom_kit(&["explode"]).status.code(), Some(64));
+
+    // Stray arguments
+    assert_eq!(loom_kit(&["clear", "now"]).status.code(), Some(64));
+
+    // Missing test name
+    assert_eq!(loom_kit(&["checkpoint"]).status.code(), Some(64));
+    assert_eq!(loom_kit(&["show"]).status.code(), Some(64));
+    assert_eq!(loom_kit(&["show", "a", "b"]).status.code(), Some(64));
+    assert_eq!(loom_kit(&["report"]).status.code(), Some(64));
+    assert_eq!(loom_kit(&["promote"]).status.code(), Some(64));
+
+    // Test names that would escape the artifacts directory
+    for name in ["../escape", "a/b", "..", ""] {
+        let output = loom_kit(&["trace", name]);
+        assert_eq!(output.status.code(), Some(64), "accepted {name:?}");
+    }
+}
+
+#[test]
+fn promote_needs_a_checkpoint() {
+    let output = loom_kit(&["promote", "no_such_test"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no checkpoint for no_such_test"));
+    assert!(!std::path::Path::new("tests/loom_corpus/no_such_test").exists());
+}
+
+#[test]
+fn help() {
+    let output = loom_kit(&["help"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("list-artifacts"));
+}
+
+#[test]
+fn checkpoint_runs_only_the_named_test() {
+    // A target dir of its own keeps the loom build from clobbering this one,
+    // and the flags this one was built with would be added to loom's
+    let output = Command::new(env!("CARGO_BIN_EXE_loom-kit"))
+        .args(["checkpoint", "shared_prefix", "--test", "cli"])
+        .env_remove("RUSTFLAGS")
+        .env(
+            "CARGO_TARGET_DIR",
+            Path::new(env!("CARGO_TARGET_TMPDIR")).join("loom-kit"),
+        )
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(
+        output.status.success(),
+        "{stdout}{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.contains("running 1 test"), "{stdout}");
+    assert!(stdout.contains("test shared_prefix ... ok"), "{stdout}");
+}
+
+/// Run by `checkpoint_runs_only_the_named_test`, along with
+/// `shared_prefix_longer` if the name were only a substring filter.
+#[test]
+fn shared_prefix() {
+    concurrent_test!({
+        let flag = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let flag = flag.clone();
+            thread::spawn(move || flag.store(true, Release))
+        };
+        thread.join().unwrap();
+        assert!(flag.load(Acquire));
+    });
+}
+
+#[test]
+fn shared_prefix_longer() {
+    concurrent_test!({
+        let flag = Arc::new(AtomicBool::new(false));
+        thread::spawn(move || flag.store(true, Release));
+    });
+}
@@ -0,0 +1,202 @@
+//! Targets for the tests of `sequential`, shared by `tests/sequential.rs`
+//! and `tests/sequential_proptest.rs`, which each use some of them.
+
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+
+use rust_atomics::channels::safe::SafeChannel;
+use rust_atomics::linearizability::Spec;
+use rust_atomics::locks::{Mutex, RwLock, SpinLock};
+use rust_atomics::rng::Rng;
+use rust_atomics::sequential::Target;
+#[cfg(loom)]
+use rust_atomics::sync::atomic::AtomicUsize;
+#[cfg(loom)]
+use rust_atomics::sync::atomic::Ordering::{Acquire, Release};
+
+/// A FIFO queue whose receives wait for a message.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Queue(pub VecDeque<usize>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueueOp {
+    Send(usize),
+    Receive,
+}
+
+impl Spec for Queue {
+    type Op = QueueOp;
+    type Ret = Option<usize>;
+
+    fn apply(&mut self, op: &QueueOp) -> Option<usize> {
+        match *op {
+            QueueOp::Send(message) => {
+                self.0.push_back(message);
+                None
+            }
+            QueueOp::Receive => self.0.pop_front(),
+        }
+    }
+
+    fn blocks(&self, op: &QueueOp) -> bool {
+        matches!(op, QueueOp::Receive) && self.0.is_empty()
+    }
+}
+
+pub struct Channel;
+
+impl Target for Channel {
+    type Spec = Queue;
+    type Object = SafeChannel<usize>;
+
+    fn new() -> SafeChannel<usize> {
+        SafeChannel::new()
+    }
+
+    fn apply(channel: &SafeChannel<usize>, op: &QueueOp) -> Option<usize> {
+        match *op {
+            QueueOp::Send(message) => {
+                channel.send(message);
+                None
+            }
+            QueueOp::Receive => Some(channel.receive()),
+        }
+    }
+
+    fn arbitrary_op(rng: &mut Rng) -> QueueOp {
+        if rng.one_in(2) {
+            QueueOp::Send(rng.below(3) as usize)
+        } else {
+            QueueOp::Receive
+        }
+    }
+}
+
+/// A counter behind a lock.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Counter(pub usize);
+
+/// Every operation returns the count it saw.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CounterOp {
+    Get,
+    Add(usize),
+}
+
+impl Spec for Counter {
+    type Op = CounterOp;
+    type Ret = usize;
+
+    fn apply(&mut self, op: &CounterOp) -> usize {
+        let count = self.0;
+        if let CounterOp::Add(n) = *op {
+            self.0 += n;
+        }
+        count
+    }
+}
+
+pub fn arbitrary_counter_op(rng: &mut Rng) -> CounterOp {
+    if rng.one_in(3) {
+        CounterOp::Get
+    } else {
+        CounterOp::Add(1 + rng.below(2) as usize)
+    }
+}
+
+/// Runs a counter op on the count behind a guard.
+pub fn count(count: &mut usize, op: &CounterOp) -> usize {
+    let seen = *count;
+    if let CounterOp::Add(n) = *op {
+        *count += n;
+    }
+    seen
+}
+
+pub struct LockedCounter;
+
+impl Target for LockedCounter {
+    type Spec = Counter;
+    type Object = Mutex<usize>;
+
+    fn new() -> Mutex<usize> {
+        Mutex::new(0)
+    }
+
+    fn apply(lock: &Mutex<usize>, op: &CounterOp) -> usize {
+        count(&mut lock.lock(), op)
+    }
+
+    fn arbitrary_op(rng: &mut Rng) -> CounterOp {
+        arbitrary_counter_op(rng)
+    }
+}
+
+pub struct SpinCounter;
+
+impl Target for SpinCounter {
+    type Spec = Counter;
+    type Object = SpinLock<usize>;
+
+    fn new() -> SpinLock<usize> {
+        SpinLock::new(0)
+    }
+
+    fn apply(lock: &SpinLock<usize>, op: &CounterOp) -> usize {
+        count(&mut lock.lock(), op)
+    }
+
+    fn arbitrary_op(rng: &mut Rng) -> CounterOp {
+        arbitrary_counter_op(rng)
+    }
+}
+
+pub struct RwCounter;
+
+impl Target for RwCounter {
+    type Spec = Counter;
+    type Object = RwLock<usize>;
+
+    fn new() -> RwLock<usize> {
+        RwLock::new(0)
+    }
+
+    fn apply(lock: &RwLock<usize>, op: &CounterOp) -> usize {
+        match op {
+            CounterOp::Get => *lock.read(),
+            CounterOp::Add(_) => count(&mut lock.write(), op),
+        }
+    }
+
+    fn arbitrary_op(rng: &mut Rng) -> CounterOp {
+        arbitrary_counter_op(rng)
+    }
+}
+
+/// A counter that loads and stores separately, so two adds can see the
+/// same count.
+#[cfg(loom)]
+pub struct RacyCounter;
+
+#[cfg(loom)]
+impl Target for RacyCounter {
+    type Spec = Counter;
+    type Object = AtomicUsize;
+
+    fn new() -> AtomicUsize {
+        AtomicUsize::new(0)
+    }
+
+    fn apply(counter: &AtomicUsize, op: &CounterOp) -> usize {
+        let seen = counter.load(Acquire);
+        if let CounterOp::Add(n) = *op {
+            counter.store(seen + n, Release);
+        }
+        seen
+    }
+
+    fn arbitrary_op(_: &mut Rng) -> CounterOp {
+        CounterOp::Add(1)
+    }
+}
@@ -0,0 +1,195 @@
+use rust_atomics::checkpoint::Checkpoint;
+use rust_atomics::happens_before::{EdgeKind, Graph};
+use rust_atomics::trace;
+
+/// The edges of `graph` between nodes with the given labels.
+fn edges(graph: &Graph, kind: EdgeKind) -> Vec<(&str, &str)> {
+    graph
+        .edges
+        .iter()
+        .filter(|edge| edge.kind == kind)
+        .map(|edge| {
+            (
+                graph.nodes[edge.from].label.as_str(),
+                graph.nodes[edge.to].label.as_str(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn relaxed_load_does_not_synchronize() {
+    let executions = trace::parse(include_str!("fixtures/release_and_acquire_incorrect.log"));
+    let checkpoint: Checkpoint = include_str!("fixtures/release_and_acquire_incorrect.json")
+        .parse()
+        .unwrap();
+    let graph = Graph::build(trace::failing(&executions).unwrap(), Some(&checkpoint));
+
+    // The flag load is in the checkpoint and reads the Release store...
+    assert_eq!(
+        edges(&graph, EdgeKind::ReadsFrom { inferred: false }),
+        [("store atomic#2 Release", "load atomic#2 Relaxed")]
+    );
+    // ...but being Relaxed, only the spawn synchronizes
+    assert_eq!(
+        edges(&graph, EdgeKind::SynchronizesWith),
+        [("spawn thread 1", "store atomic#0 Relaxed")]
+    );
+    // So the data load may still read the initial value, and does
+    assert_eq!(
+        edges(&graph, EdgeKind::ReadsFrom { inferred: true }),
+        [("atomic#0 initial value", "load atomic#0 Relaxed")]
+    );
+
+    assert_eq!(
+        edges(&graph, EdgeKind::ProgramOrder),
+        [
+            ("store atomic#0 Relaxed", "store atomic#2 Release"),
+            ("store atomic#2 Release", "done"),
+            ("spawn thread 1", "load atomic#2 Relaxed"),
+            ("load atomic#2 Relaxed", "load atomic#0 Relaxed"),
+        ]
+    );
+
+    let failed: Vec<_> = graph.nodes.iter().filter(|node| node.failed).collect();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].label, "load atomic#0 Relaxed");
+    assert_eq!(failed[0].thread, Some(0));
+}
+
+#[test]
+fn acquire_load_synchronizes() {
+    let log = "\
+ INFO loom::model:  ================== Iteration 1 ==================
+TRACE iter{1}:thread{id=0}: loom::rt: spawn thread=Id(1)
+TRACE iter{1}:thread{id=1}: loom::rt::atomic: Atomic::store state=Ref<loom::rt::atomic::State>(0) ordering=Relaxed
+TRACE iter{1}:thread{id=1}: loom::rt::atomic: Atomic::store state=Ref<loom::rt::atomic::State>(1) ordering=Release
+TRACE iter{1}:thread{id=0}: loom::rt::atomic: Atomic::load state=Ref<loom::rt::atomic::State>(1) ordering=Acquire
+TRACE iter{1}:thread{id=0}: loom::rt::atomic: Atomic::load state=Ref<loom::rt::atomic::State>(0) ordering=Relaxed
+";
+    // Only the flag load was recorded, reading the second store in the
+    // atomic's history
+    let checkpoint: Checkpoint = r#"{
+        "preemption_bound": null,
+        "branches": {"entries": [
+            {"Load": {"values": [0, 1, 0, 0, 0, 0, 0], "pos": 1, "len": 2, "exploring": true}}
+        ]}
+    }"#
+    .parse()
+    .unwrap();
+    let executions = trace::parse(log);
+    let graph = Graph::build(&executions[0], Some(&checkpoint));
+
+    assert_eq!(
+        edges(&graph, EdgeKind::SynchronizesWith),
+        [
+            ("spawn thread 1", "store atomic#0 Relaxed"),
+            ("store atomic#1 Release", "load atomic#1 Acquire"),
+        ]
+    );
+    // Having synchronized, the data load can only see the newest data
+    assert_eq!(
+        edges(&graph, EdgeKind::ReadsFrom { inferred: true }),
+        [("store atomic#0 Relaxed", "load atomic#0 Relaxed")]
+    );
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph execution {\n"), "{dot}");
+    assert!(dot.contains("subgraph cluster_thread_1 {"), "{dot}");
+    assert!(dot.contains("label=\"sw\""), "{dot}");
+    assert!(dot.contains("label=\"rf?\""), "{dot}");
+}
+
+#[test]
+fn spin_loop_moves_on_after_yield() {
+    let log = "\
+ INFO loom::model:  ================== Iteration 1 ==================
+TRACE iter{1}:thread{id=0}: loom::rt: spawn thread=Id(1)
+TRACE iter{1}:thread{id=0}: loom::rt::atomic: Atomic::load state=Ref<loom::rt::atomic::State>(0) ordering=Relaxed
+TRACE iter{1}:thread{id=1}: loom::rt::atomic: Atomic::store state=Ref<loom::rt::atomic::State>(0) ordering=Relaxed
+TRACE iter{1}:thread{id=0}: loom::rt: yield_now thread=Id(0) switch=true
+TRACE iter{1}:thread{id=0}: loom::rt::atomic: Atomic::load state=Ref<loom::rt::atomic::State>(0) ordering=Relaxed
+";
+    let executions = trace::parse(log);
+    let graph = Graph::build(&executions[0], None);
+
+    assert_eq!(
+        edges(&graph, EdgeKind::ReadsFrom { inferred: true }),
+        [
+            ("atomic#0 initial value", "load atomic#0 Relaxed"),
+            ("store atomic#0 Relaxed", "load atomic#0 Relaxed"),
+        ]
+    );
+}
+
+/// Thread 1's compare-exchange reads the initial value while thread 0
+/// stores, then it loads the flag again.
+const COMPARE_EXCHANGE: &str = "\
+ INFO loom::model:  ================== Iteration 1 ==================
+TRACE iter{1}:thread{id=0}: loom::rt: spawn thread=Id(1)
+TRACE iter{1}:thread{id=1}: loom::rt::atomic: Atomic::rmw state=Ref<loom::rt::atomic::State>(0) success=AcqRel failure=Relaxed
+TRACE iter{1}:thread{id=0}: loom::rt::atomic: Atomic::store state=Ref<loom::rt::atomic::State>(0) ordering=Release
+TRACE iter{1}:thread{id=1}: loom::rt::atomic: Atomic::load state=Ref<loom::rt::atomic::State>(0) ordering=Acquire
+";
+
+/// The compare-exchange reads the first store in the atomic's history, and
+/// the load the second.
+fn compare_exchange_checkpoint() -> Checkpoint {
+    r#"{
+        "preemption_bound": null,
+        "branches": {"entries": [
+            {"Load": {"values": [0, 0, 0, 0, 0, 0, 0], "pos": 0, "len": 1, "exploring": false}},
+            {"Load": {"values": [0, 1, 0, 0, 0, 0, 0], "pos": 1, "len": 2, "exploring": true}}
+        ]}
+    }"#
+    .parse()
+    .unwrap()
+}
+
+#[test]
+fn failed_compare_exchange_does_not_store() {
+    let log = COMPARE_EXCHANGE.replace(
+        "failure=Relaxed\n",
+        "failure=Relaxed\n\
+         TRACE iter{1}:thread{id=1}: rust_atomics::sync::atomic: Atomic::rmw done stored=false\n",
+    );
+    let executions = trace::parse(&log);
+    let graph = Graph::build(&executions[0], Some(&compare_exchange_checkpoint()));
+
+    // So the second store is thread 0's, which the load synchronizes with
+    assert_eq!(
+        edges(&graph, EdgeKind::ReadsFrom { inferred: false }),
+        [
+            (
+                "atomic#0 initial value",
+                "rmw atomic#0 AcqRel/Relaxed (failed)"
+            ),
+            ("store atomic#0 Release", "load atomic#0 Acquire"),
+        ]
+    );
+    assert_eq!(
+        edges(&graph, EdgeKind::SynchronizesWith),
+        [
+            ("spawn thread 1", "rmw atomic#0 AcqRel/Relaxed (failed)"),
+            ("store atomic#0 Release", "load atomic#0 Acquire"),
+        ]
+    );
+}
+
+#[test]
+fn loads_after_an_rmw_that_may_not_have_stored_are_inferred() {
+    let executions = trace::parse(COMPARE_EXCHANGE);
+    let graph = Graph::build(&executions[0], Some(&compare_exchange_checkpoint()));
+
+    // The rmw is taken to have stored, which leaves the checkpoint's choice
+    // for the load pointing at a store that may not exist, so the load is
+    // inferred instead
+    assert_eq!(
+        edges(&graph, EdgeKind::ReadsFrom { inferred: false }),
+        [("atomic#0 initial value", "rmw atomic#0 AcqRel/Relaxed")]
+    );
+    assert_eq!(
+        edges(&graph, EdgeKind::ReadsFrom { inferred: true }),
+        [("rmw atomic#0 AcqRel/Relaxed", "load atomic#0 Acquire")]
+    );
+}
@@ -0,0 +1,230 @@
+use std::collections::VecDeque;
+
+use rust_atomics::channels::safe::SafeChannel;
+use rust_atomics::concurrent_test;
+use rust_atomics::linearizability::{History, Spec};
+use rust_atomics::locks::RwLock;
+use rust_atomics::sync::atomic::AtomicUsize;
+use rust_atomics::sync::atomic::Ordering::{Acquire, Release};
+use rust_atomics::sync::{Arc, thread};
+
+/// A single value, read and written whole.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+struct Register(usize);
+
+#[derive(Debug, Clone)]
+enum RegisterOp {
+    Read,
+    Write(usize),
+}
+
+impl Spec for Register {
+    type Op = RegisterOp;
+    type Ret = Option<usize>;
+
+    fn apply(&mut self, op: &RegisterOp) -> Option<usize> {
+        match *op {
+            RegisterOp::Read => Some(self.0),
+            RegisterOp::Write(value) => {
+                self.0 = value;
+                None
+            }
+        }
+    }
+}
+
+/// A FIFO queue whose receives wait for a message.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+struct Queue(VecDeque<usize>);
+
+#[derive(Debug, Clone)]
+enum QueueOp {
+    Send(usize),
+    Receive,
+}
+
+impl Spec for Queue {
+    type Op = QueueOp;
+    type Ret = Option<usize>;
+
+    fn apply(&mut self, op: &QueueOp) -> Option<usize> {
+        match *op {
+            QueueOp::Send(message) => {
+                self.0.push_back(message);
+                None
+            }
+            // A receive on an empty queue would block, which no completed
+            // receive can have done, so `None` never matches
+            QueueOp::Receive => self.0.pop_front(),
+        }
+    }
+}
+
+/// A counter whose increments return the previous count.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+struct Counter(usize);
+
+#[derive(Debug, Clone)]
+struct Increment;
+
+impl Spec for Counter {
+    type Op = Increment;
+    type Ret = usize;
+
+    fn apply(&mut self, _: &Increment) -> usize {
+        self.0 += 1;
+        self.0 - 1
+    }
+}
+
+// Histories built by hand, on one thread outside any model
+
+#[test]
+#[cfg(not(any(loom, shuttle)))]
+fn overlapping_operations_linearize_either_way() {
+    let history = History::<Register>::new();
+    let write = history.invoke(RegisterOp::Write(1));
+    let old = history.invoke(RegisterOp::Read);
+    let new = history.invoke(RegisterOp::Read);
+    history.respond(old, Some(0));
+    history.respond(new, Some(1));
+    history.respond(write, None);
+
+    assert_eq!(history.check(Register(0)).unwrap(), [1, 0, 2]);
+}
+
+#[test]
+#[cfg(not(any(loom, shuttle)))]
+fn stale_read_is_not_linearizable() {
+    let history = History::<Register>::new();
+    history.call(RegisterOp::Write(1), || None);
+    history.call(RegisterOp::Read, || Some(0));
+
+    let error = history.check(Register(0)).unwrap_err();
+    assert_eq!(error.longest, [0]);
+    assert_eq!(error.state, Register(1));
+
+    let message = error.to_string();
+    assert!(
+        message.starts_with("history is not linearizable:"),
+        "{message}"
+    );
+    assert!(message.contains("#1 ThreadId("), "{message}");
+    assert!(message.contains(": Read -> Some(0) [2, 3]"), "{message}");
+}
+
+#[test]
+#[cfg(not(any(loom, shuttle)))]
+fn unfinished_operations_may_take_effect() {
+    let history = History::<Register>::new();
+    let _write = history.invoke(RegisterOp::Write(1));
+    history.call(RegisterOp::Read, || Some(1));
+    assert_eq!(history.check(Register(0)).unwrap(), [0, 1]);
+
+    // ... or not at all
+    let history = History::<Register>::new();
+    let _write = history.invoke(RegisterOp::Write(1));
+    history.call(RegisterOp::Read, || Some(0));
+    assert_eq!(history.check(Register(0)).unwrap(), [1]);
+}
+
+#[test]
+#[cfg(not(any(loom, shuttle)))]
+fn long_histories_are_checked_quickly() {
+    // Without memoization all 12! orders of the overlapping writes would be
+    // tried before giving up on the read
+    let history = History::<Register>::new();
+    let writes: Vec<_> = (0..12)
+        .map(|_| history.invoke(RegisterOp::Write(1)))
+        .collect();
+    for write in writes {
+        history.respond(write, None);
+    }
+    history.call(RegisterOp::Read, || Some(0));
+
+    let error = history.check(Register(0)).unwrap_err();
+    assert_eq!(error.longest.len(), 12);
+}
+
+#[test]
+fn safe_channel_is_linearizable() {
+    concurrent_test!({
+        let channel = Arc::new(SafeChannel::new());
+        let history = History::new();
+
+        let senders: Vec<_> = [10, 20]
+            .into_iter()
+            .map(|message| {
+                let channel = channel.clone();
+                let history = history.clone();
+                thread::spawn(move || {
+                    history.call(QueueOp::Send(message), || {
+                        channel.send(message);
+                        None
+                    });
+                })
+            })
+            .collect();
+
+        history.call(QueueOp::Receive, || Some(channel.receive()));
+        for sender in senders {
+            sender.join().unwrap();
+        }
+        history.call(QueueOp::Receive, || Some(channel.receive()));
+
+        history.assert_linearizable(Queue::default());
+    });
+}
+
+#[test]
+fn rwlock_is_linearizable() {
+    concurrent_test!({
+        let lock = Arc::new(RwLock::new(0));
+        let history = History::new();
+
+        let writer = {
+            let lock = lock.clone();
+            let history = history.clone();
+            thread::spawn(move || {
+                history.call(RegisterOp::Write(1), || {
+                    *lock.write() = 1;
+                    None
+                });
+            })
+        };
+
+        history.call(RegisterOp::Read, || Some(*lock.read()));
+        writer.join().unwrap();
+        history.call(RegisterOp::Read, || Some(*lock.read()));
+
+        history.assert_linearizable(Register(0));
+    });
+}
+
+#[test]
+fn racy_counter_is_not_linearizable() {
+    // Loading and storing separately lets two increments read the same count
+    concurrent_test!(expected_panic = "history is not linearizable", {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let history = History::new();
+
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let counter = counter.clone();
+                let history = history.clone();
+                thread::spawn(move || {
+                    history.call(Increment, || {
+                        let count = counter.load(Acquire);
+                        counter.store(count + 1, Release);
+                        count
+                    });
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        history.assert_linearizable(Counter(0));
+    });
+}
@@ -0,0 +1,112 @@
+use std::fmt::Debug;
+
+use rust_atomics::litmus::{
+    IndependentReads, Litmus, LoadBuffering, MessagePassing, ReadReadCoherence, Report,
+    StoreBuffering, TwoPlusTwoWrites, WriteReadCausality,
+};
+use rust_atomics::model::Builder;
+use rust_atomics::sync::atomic::Ordering::{self, AcqRel, Acquire, Relaxed, Release, SeqCst};
+
+const ORDERINGS: [Ordering; 3] = [Relaxed, AcqRel, SeqCst];
+
+/// Checks a report against the memory model.
+///
+/// Loom doesn't implement all of C++11, so under loom the known differences
+/// are spelled out: outcomes it produces although they are forbidden, and
+/// allowed outcomes it can never produce. Real hardware must never show a
+/// forbidden outcome, but may well miss allowed ones.
+#[track_caller]
+fn expect<O: Ord + Debug>(report: &Report<O>, loom_forbidden: &[O], loom_missing: &[O]) {
+    if cfg!(loom) {
+        assert_eq!(report.forbidden(), loom_forbidden.iter().collect::<Vec<_>>(), "{report}");
+        assert_eq!(report.missing(), loom_missing.iter().collect::<Vec<_>>(), "{report}");
+    } else {
+        report.assert_matches();
+    }
+}
+
+#[test]
+fn message_passing() {
+    for order in ORDERINGS {
+        expect(&MessagePassing::uniform(order).run(), &[], &[]);
+    }
+
+    // Releasing the flag is not enough if the load doesn't acquire it
+    let mp = MessagePassing {
+        store_data: Relaxed,
+        store_flag: Release,
+        load_flag: Relaxed,
+        load_data: Relaxed,
+    };
+    assert!(mp.allowed().contains(&(1, 0)));
+    expect(&mp.run(), &[], &[]);
+}
+
+#[test]
+fn store_buffering() {
+    expect(&StoreBuffering::uniform(Relaxed).run(), &[], &[]);
+    expect(&StoreBuffering::uniform(AcqRel).run(), &[], &[]);
+
+    // Loom treats SeqCst loads and stores like AcqRel
+    expect(&StoreBuffering::uniform(SeqCst).run(), &[(0, 0)], &[]);
+}
+
+#[test]
+fn load_buffering() {
+    // Loom never lets a load read from a store that hasn't executed yet
+    expect(&LoadBuffering::uniform(Relaxed).run(), &[], &[(1, 1)]);
+    expect(&LoadBuffering::uniform(AcqRel).run(), &[], &[]);
+    expect(&LoadBuffering::uniform(SeqCst).run(), &[], &[]);
+}
+
+#[test]
+fn independent_reads() {
+    // Four threads is a large state space, but two preemptions are enough to
+    // produce every outcome
+    let mut builder = Builder::new();
+    builder.preemption_bound(2);
+
+    expect(&IndependentReads::uniform(Relaxed).run_with(&builder), &[], &[]);
+    expect(&IndependentReads::uniform(AcqRel).run_with(&builder), &[], &[]);
+
+    // Loom treats SeqCst loads and stores like AcqRel
+    expect(
+        &IndependentReads::uniform(SeqCst).run_with(&builder),
+        &[[1, 0, 1, 0]],
+        &[],
+    );
+}
+
+#[test]
+fn two_plus_two_writes() {
+    expect(&TwoPlusTwoWrites::uniform(Relaxed).run(), &[], &[]);
+    expect(&TwoPlusTwoWrites::uniform(AcqRel).run(), &[], &[]);
+
+    // Loom treats SeqCst stores like Release
+    expect(&TwoPlusTwoWrites::uniform(SeqCst).run(), &[(1, 1)], &[]);
+}
+
+#[test]
+fn write_read_causality() {
+    for order in ORDERINGS {
+        expect(&WriteReadCausality::uniform(order).run(), &[], &[]);
+    }
+
+    // Synchronizing on `y` alone is enough to forbid (1, 1, 0)
+    let wrc = WriteReadCausality {
+        store_x: Relaxed,
+        load_x_relay: Relaxed,
+        store_y: Release,
+        load_y: Acquire,
+        load_x: Relaxed,
+    };
+    assert!(!wrc.allowed().contains(&[1, 1, 0]));
+    expect(&wrc.run(), &[], &[]);
+}
+
+#[test]
+fn read_read_coherence() {
+    for order in ORDERINGS {
+        expect(&ReadReadCoherence::uniform(order).run(), &[], &[]);
+    }
+}
@@ -0,0 +1,109 @@
+use rust_atomics::concurrent_test;
+use rust_atomics::locks::{Mutex, RwLock, SpinLock};
+use rust_atomics::sync::{Arc, thread};
+
+#[test]
+fn consistent_order_passes() {
+    concurrent_test!({
+        let locks = Arc::new((Mutex::new(0), SpinLock::new(0)));
+
+        let thread = {
+            let locks = locks.clone();
+            thread::spawn(move || {
+                let _outer = locks.0.lock();
+                *locks.1.lock() += 1;
+            })
+        };
+        {
+            let _outer = locks.0.lock();
+            *locks.1.lock() += 1;
+        }
+        thread.join().unwrap();
+
+        assert_eq!(*locks.1.lock(), 2);
+    });
+}
+
+#[test]
+fn inversion_is_reported_without_deadlocking() {
+    // The two orders never overlap, so this run can't hang, but another
+    // could
+    concurrent_test!(expected_panic = "lock order inversion", {
+        let locks = Arc::new((Mutex::new(()), Mutex::new(())));
+        {
+            let locks = locks.clone();
+            thread::spawn(move || {
+                let _a = locks.0.lock();
+                let _b = locks.1.lock();
+            })
+            .join()
+            .unwrap();
+        }
+
+        let _b = locks.1.lock();
+        let _a = locks.0.lock();
+    });
+}
+
+#[test]
+#[cfg(not(any(loom, shuttle)))]
+fn longer_cycles_are_reported_with_every_acquisition() {
+    use std::panic::{self, AssertUnwindSafe};
+
+    let a = Mutex::new(());
+    let b = SpinLock::new(());
+    let c = RwLock::new(());
+    {
+        let _a = a.lock();
+        let _b = b.lock();
+    }
+    {
+        let _b = b.lock();
+        let _c = c.read();
+    }
+
+    let payload = panic::catch_unwind(AssertUnwindSafe(|| {
+        let _c = c.write();
+        let _a = a.lock();
+    }))
+    .unwrap_err();
+    let message = payload.downcast_ref::<String>().unwrap();
+
+    assert!(message.starts_with("lock order inversion:"), "{message}");
+    assert_eq!(message.matches(" then ").count(), 3, "{message}");
+    for edge in ["Mutex #", "SpinLock #", "RwLock #"] {
+        assert!(message.contains(edge), "{message}");
+    }
+    assert!(
+        message.contains("longer_cycles_are_reported_with_every_acquisition"),
+        "{message}"
+    );
+}
+
+#[test]
+fn taking_a_held_lock_again_is_reported() {
+    concurrent_test!(expected_panic = "which it already holds", {
+        let lock = RwLock::new(());
+        let _first = lock.read();
+        let _second = lock.read();
+    });
+}
+
+#[test]
+#[cfg(not(any(loom, shuttle)))]
+fn guards_dropped_elsewhere_release_the_thread_that_took_them() {
+    use std::panic::{self, AssertUnwindSafe};
+
+    let lock = RwLock::new(());
+    let _mine = lock.read();
+    let theirs = std::thread::scope(|s| s.spawn(|| lock.read()).join().unwrap());
+    // Releases the other thread's read, so this one's is still held
+    drop(theirs);
+
+    let payload = panic::catch_unwind(AssertUnwindSafe(|| {
+        let _again = lock.read();
+    }))
+    .unwrap_err();
+    let message = payload.downcast_ref::<String>().unwrap();
+    assert!(message.contains("which it already holds"), "{message}");
+}
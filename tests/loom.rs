@@ -1,14 +1,16 @@
 use rust_atomics::channels::safe::SafeChannel;
-use rust_atomics::locks::SpinLock;
-use rust_atomics::{concurrent_test, sync};
+use rust_atomics::locks::{Mutex, RwLock, SpinLock};
+use rust_atomics::outcomes::{assert_outcomes, explore_outcomes};
+use rust_atomics::{attr, concurrent_test, set, sync};
 use sync::Arc;
 use sync::atomic::Ordering::{Acquire, Relaxed, Release, SeqCst};
-use sync::atomic::{AtomicBool, AtomicU64, AtomicUsize};
+use sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, fence};
+use sync::futex::{wait, wake_all, wake_one};
 use sync::spin_loop;
 use sync::thread;
 #[test]
 fn test_concurrent_logic() {
-    concurrent_test!({
+    concurrent_test!(expected_panic = "left == right", {
         // Create an atomic counter shared between threads
         let v1 = Arc::new(AtomicUsize::new(0));
         let v2 = v1.clone();
@@ -29,6 +31,311 @@ fn test_concurrent_logic() {
     });
 }
 
+#[test]
+#[cfg(loom)]
+fn replay_checkpoint() {
+    use rust_atomics::model::Builder;
+
+    // Counts executions across the whole model, so it must not be a loom type
+    static EXECUTIONS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    fn body() {
+        EXECUTIONS.fetch_add(1, SeqCst);
+
+        let v1 = Arc::new(AtomicUsize::new(0));
+        let v2 = v1.clone();
+        thread::spawn(move || v1.store(1, SeqCst));
+        assert_eq!(0, v2.load(SeqCst));
+    }
+
+    let checkpoint =
+        std::env::temp_dir().join(format!("replay_checkpoint-{}.json", std::process::id()));
+
+    // Explore until the failure, saving each interleaving before it runs
+    Builder::new()
+        .checkpoint_file(&checkpoint)
+        .checkpoint_interval(1)
+        .should_find_bug()
+        .check(body);
+
+    // Replaying runs only the failing interleaving, and fails again
+    EXECUTIONS.store(0, SeqCst);
+    Builder::new()
+        .replay(&checkpoint)
+        .should_find_bug()
+        .check(body);
+    assert_eq!(EXECUTIONS.load(SeqCst), 1);
+
+    std::fs::remove_file(&checkpoint).unwrap();
+}
+
+#[test]
+#[cfg(shuttle)]
+fn shuttle_pct_finds_the_bug() {
+    use rust_atomics::model::{Builder, Scheduler};
+
+    // `should_find_bug` lets a clean run pass under shuttle, so look at the
+    // result directly
+    let result = std::panic::catch_unwind(|| {
+        Builder::new()
+            .scheduler(Scheduler::Pct { depth: 2 })
+            .iterations(100)
+            .check(|| {
+                let v1 = Arc::new(AtomicUsize::new(0));
+                let v2 = v1.clone();
+                thread::spawn(move || v1.store(1, SeqCst));
+                assert_eq!(0, v2.load(SeqCst));
+            })
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+#[cfg(not(any(loom, shuttle)))]
+fn std_pct_finds_the_bug() {
+    use rust_atomics::model::{Builder, Scheduler};
+
+    // On its own the spawned thread practically never stores before the
+    // load, but PCT often makes the main thread back off at the load
+    let result = std::panic::catch_unwind(|| {
+        Builder::new()
+            .scheduler(Scheduler::Pct { depth: 2 })
+            .iterations(100)
+            .check(|| {
+                let v1 = Arc::new(AtomicUsize::new(0));
+                let v2 = v1.clone();
+                thread::spawn(move || v1.store(1, SeqCst));
+                assert_eq!(0, v2.load(SeqCst));
+            })
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+#[cfg(loom)]
+fn passing_checkpoint_is_not_replayed() {
+    use rust_atomics::artifacts;
+    use rust_atomics::model::Builder;
+
+    static EXECUTIONS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    fn body() {
+        EXECUTIONS.fetch_add(1, SeqCst);
+
+        let v1 = Arc::new(AtomicUsize::new(0));
+        let v2 = v1.clone();
+        thread::spawn(move || v1.store(1, SeqCst));
+        v2.load(SeqCst);
+    }
+
+    // Written where `loom-kit checkpoint` would, by a run that passes
+    let checkpoint = artifacts::checkpoint_path("passing_checkpoint_is_not_replayed");
+    std::fs::create_dir_all(artifacts::DIR).unwrap();
+    Builder::new()
+        .checkpoint_file(&checkpoint)
+        .checkpoint_interval(1)
+        .check(body);
+    assert!(!checkpoint.exists());
+
+    // So the next run explores every interleaving again
+    EXECUTIONS.store(0, SeqCst);
+    Builder::new().check(body);
+    assert!(EXECUTIONS.load(SeqCst) > 1);
+}
+
+#[test]
+#[cfg(loom)]
+fn budget_stops_exploring() {
+    use rust_atomics::model::Builder;
+
+    static EXECUTIONS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    // Two threads racing two increments each have far more than ten
+    // interleavings, so this stops early and passes as inconclusive
+    Builder::new().execution_budget(10).check(|| {
+        EXECUTIONS.fetch_add(1, SeqCst);
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let counter = counter.clone();
+                thread::spawn(move || {
+                    counter.fetch_add(1, Relaxed);
+                    counter.fetch_add(1, Relaxed);
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    });
+    assert_eq!(EXECUTIONS.load(SeqCst), 10);
+}
+
+#[test]
+#[cfg(loom)]
+fn inconclusive_checkpoint_is_kept() {
+    use rust_atomics::artifacts;
+    use rust_atomics::model::Builder;
+
+    static EXECUTIONS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    fn body() {
+        EXECUTIONS.fetch_add(1, SeqCst);
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let other = counter.clone();
+        let thread = thread::spawn(move || {
+            other.fetch_add(1, Relaxed);
+            other.fetch_add(1, Relaxed);
+        });
+        counter.fetch_add(1, Relaxed);
+        counter.fetch_add(1, Relaxed);
+        thread.join().unwrap();
+    }
+
+    Builder::new().check(body);
+    let all = EXECUTIONS.swap(0, SeqCst);
+
+    // Written where `loom-kit checkpoint` would, by a run that runs out of
+    // budget
+    let checkpoint = artifacts::checkpoint_path("inconclusive_checkpoint_is_kept");
+    std::fs::create_dir_all(artifacts::DIR).unwrap();
+    Builder::new()
+        .checkpoint_file(&checkpoint)
+        .checkpoint_interval(1)
+        .execution_budget(3)
+        .check(body);
+    assert!(checkpoint.exists());
+
+    // So exploring again resumes where it stopped
+    EXECUTIONS.store(0, SeqCst);
+    Builder::new()
+        .checkpoint_file(&checkpoint)
+        .checkpoint_interval(1)
+        .check(body);
+    assert_eq!(EXECUTIONS.load(SeqCst), all - 3);
+    assert!(!checkpoint.exists());
+}
+
+#[test]
+#[cfg(loom)]
+fn corpus_is_replayed_first() {
+    use rust_atomics::model::Builder;
+
+    static EXECUTIONS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    fn body() {
+        EXECUTIONS.fetch_add(1, SeqCst);
+
+        let v1 = Arc::new(AtomicUsize::new(0));
+        let v2 = v1.clone();
+        thread::spawn(move || v1.store(1, SeqCst));
+        assert_eq!(0, v2.load(SeqCst));
+    }
+
+    // tests/loom_corpus/corpus_is_replayed_first/ holds the failing
+    // interleaving, which exploring wouldn't reach until the second execution
+    Builder::new().should_find_bug().check(body);
+    assert_eq!(EXECUTIONS.load(SeqCst), 1);
+}
+
+#[test]
+#[cfg(loom)]
+fn minimize_failing_schedule() {
+    use rust_atomics::checkpoint::Checkpoint;
+    use rust_atomics::model::Builder;
+
+    // Fails either when the writer runs to completion before the first read,
+    // or when the last two reads each land right after a store. Loom tries
+    // late preemptions first, so it finds the second way first.
+    fn body() {
+        let x = Arc::new(AtomicUsize::new(0));
+        let x2 = x.clone();
+        let t = thread::spawn(move || {
+            x2.store(1, SeqCst);
+            x2.store(2, SeqCst);
+            x2.store(3, SeqCst);
+        });
+        let a = x.load(SeqCst);
+        let b = x.load(SeqCst);
+        let c = x.load(SeqCst);
+        t.join().unwrap();
+        assert!(a != 3 && (b, c) != (1, 2));
+    }
+
+    let found = |minimize| {
+        let checkpoint = std::env::temp_dir().join(format!(
+            "minimize_failing_schedule-{minimize}-{}.json",
+            std::process::id()
+        ));
+        Builder::new()
+            .checkpoint_file(&checkpoint)
+            .checkpoint_interval(1)
+            .minimize(minimize)
+            .should_find_bug()
+            .check(body);
+        let preemptions = Checkpoint::load(&checkpoint).unwrap().preemptions().len();
+        (checkpoint, preemptions)
+    };
+
+    let (checkpoint, first) = found(false);
+    std::fs::remove_file(&checkpoint).unwrap();
+    assert_eq!(first, 4);
+
+    let (checkpoint, fewest) = found(true);
+    assert_eq!(fewest, 1);
+    // The saved schedule still fails on its own
+    Builder::new()
+        .replay(&checkpoint)
+        .should_find_bug()
+        .check(body);
+    std::fs::remove_file(&checkpoint).unwrap();
+}
+
+#[test]
+#[cfg(loom)]
+fn minimize_counts_the_failing_execution() {
+    use rust_atomics::checkpoint::Checkpoint;
+    use rust_atomics::model::Builder;
+
+    // Fails when the first read lands between the two stores, which takes
+    // two preemptions, or when the last two reads each land right after a
+    // store, which takes more and is found first
+    fn body() {
+        let x = Arc::new(AtomicUsize::new(0));
+        let x2 = x.clone();
+        let t = thread::spawn(move || {
+            x2.store(1, SeqCst);
+            x2.store(2, SeqCst);
+        });
+        let a = x.load(SeqCst);
+        let b = x.load(SeqCst);
+        let c = x.load(SeqCst);
+        t.join().unwrap();
+        assert!(a != 1 && (b, c) != (1, 2));
+    }
+
+    // At loom's default interval the checkpoint is saved long before the
+    // failure, so it can't tell how many preemptions the failure took
+    let checkpoint = std::env::temp_dir().join(format!(
+        "minimize_counts_the_failing_execution-{}.json",
+        std::process::id()
+    ));
+    Builder::new()
+        .checkpoint_file(&checkpoint)
+        .should_find_bug()
+        .check(body);
+
+    let minimal = Checkpoint::load(&checkpoint).unwrap();
+    assert_eq!(minimal.preemptions().len(), 2);
+    Builder::new()
+        .replay(&checkpoint)
+        .should_find_bug()
+        .check(body);
+    std::fs::remove_file(&checkpoint).unwrap();
+}
+
 #[test]
 fn release_and_acquire_correct() {
     concurrent_test!({
@@ -67,7 +374,7 @@ fn release_and_acquire_correct() {
 
 #[test]
 fn release_and_acquire_incorrect() {
-    concurrent_test!({
+    concurrent_test!(should_find_bug, {
         // Shared atomic variables between threads
         let data = Arc::new(AtomicU64::new(0));
         let ready = Arc::new(AtomicBool::new(false));
@@ -100,6 +407,53 @@ fn release_and_acquire_incorrect() {
     });
 }
 
+/// Store buffering: each thread stores to one variable, then loads the other,
+/// optionally with a SeqCst fence in between.
+fn store_buffering(fenced: bool) -> (usize, usize) {
+    let x = Arc::new(AtomicUsize::new(0));
+    let y = Arc::new(AtomicUsize::new(0));
+
+    let t = thread::spawn({
+        let x = x.clone();
+        let y = y.clone();
+        move || {
+            x.store(1, Relaxed);
+            if fenced {
+                fence(SeqCst);
+            }
+            y.load(Relaxed)
+        }
+    });
+
+    y.store(1, Relaxed);
+    if fenced {
+        fence(SeqCst);
+    }
+    let r2 = x.load(Relaxed);
+    let r1 = t.join().unwrap();
+
+    (r1, r2)
+}
+
+#[test]
+fn store_buffering_fenced() {
+    // Loom only models SeqCst fully through fences; plain SeqCst loads and
+    // stores behave like AcqRel in the model, which would allow (0, 0) here
+    let outcomes = explore_outcomes(|| store_buffering(true));
+
+    // The fences are totally ordered, so whichever comes second is followed
+    // by a load that must see the other thread's store: (0, 0) is impossible
+    assert_outcomes(&outcomes, &set![(0, 1), (1, 0), (1, 1)]);
+}
+
+#[test]
+fn store_buffering_relaxed() {
+    let outcomes = explore_outcomes(|| store_buffering(false));
+
+    // Without the fences, both loads may miss the other thread's store
+    assert_outcomes(&outcomes, &set![(0, 0), (0, 1), (1, 0), (1, 1)]);
+}
+
 #[test]
 fn spin_lock() {
     concurrent_test!({
@@ -146,6 +500,166 @@ fn spin_lock() {
     });
 }
 
+#[test]
+fn no_std_lock() {
+    concurrent_test!({
+        // Create a shared lock around a tuple of (data, flag)
+        let lock = Arc::new(Mutex::new((false, false)));
+
+        let lock_writer = lock.clone();
+        let lock_reader = lock.clone();
+
+        // Thread A: Writer thread
+        let writer = thread::spawn(move || {
+            // Acquire the lock - requires Acquire ordering internally
+            let mut guard = lock_writer.lock();
+
+            // Update the data value
+            guard.0 = true;
+
+            // Set the flag indicating data was modified
+            guard.1 = true;
+
+            // When guard is dropped here, the lock is released and a
+            // contended reader is woken through the futex
+        });
+
+        // Thread B: Reader thread
+        let reader = thread::spawn(move || {
+            // Acquire the lock - requires Acquire ordering internally
+            // to synchronize with the Release in the writer thread
+            let guard = lock_reader.lock();
+
+            // If the flag is set, the data must also be set
+            if guard.1 {
+                assert!(guard.0)
+            }
+        });
+
+        // Wait for both threads to complete
+        writer.join().unwrap();
+        reader.join().unwrap();
+    });
+}
+
+#[test]
+fn no_std_rwlock() {
+    concurrent_test!({
+        // Create a shared lock around a tuple of (data, flag)
+        let lock = Arc::new(RwLock::new((false, false)));
+
+        let lock_writer = lock.clone();
+        let lock_reader = lock.clone();
+
+        // Thread A: Writer thread
+        let writer = thread::spawn(move || {
+            // Acquire the write lock - requires Acquire ordering internally
+            let mut guard = lock_writer.write();
+
+            // Update the data value
+            guard.0 = true;
+
+            // Set the flag indicating data was modified
+            guard.1 = true;
+
+            // When guard is dropped here, the write lock is released and
+            // both waiting readers and writers are woken
+        });
+
+        // Thread B: Reader thread
+        let reader = thread::spawn(move || {
+            // Acquire the read lock - requires Acquire ordering internally
+            // to synchronize with the Release in the writer thread
+            let guard = lock_reader.read();
+
+            // If the flag is set, the data must also be set
+            if guard.1 {
+                assert!(guard.0)
+            }
+        });
+
+        // Wait for both threads to complete
+        writer.join().unwrap();
+        reader.join().unwrap();
+    });
+}
+
+#[test]
+fn rwlock_writers_and_a_reader() {
+    // A writer that blocks readers, then sees the other writer take and
+    // release the lock and a reader come back in, must not sleep on the
+    // wake counter: only a writer's bit makes the last reader wake it
+    concurrent_test!(preemption_bound = 2, {
+        let lock = Arc::new(RwLock::new(0));
+
+        let writers: Vec<_> = (0..2)
+            .map(|_| {
+                let lock = lock.clone();
+                thread::spawn(move || *lock.write() += 1)
+            })
+            .collect();
+
+        // Reader: reads twice, so it can come back in after a writer
+        for _ in 0..2 {
+            drop(lock.read());
+        }
+
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(*lock.read(), 2);
+    });
+}
+
+#[test]
+fn futex_wait_wake() {
+    concurrent_test!({
+        let state = Arc::new(AtomicU32::new(0));
+
+        let waiter = thread::spawn({
+            let state = state.clone();
+            move || {
+                // Waits may return spuriously, so re-check after every wake-up
+                while state.load(Acquire) == 0 {
+                    wait(&state, 0);
+                }
+            }
+        });
+
+        // Change the value before waking, so a waiter that hasn't queued yet
+        // sees the new value instead of sleeping forever
+        state.store(1, Release);
+        wake_one(&state);
+
+        waiter.join().unwrap();
+    });
+}
+
+// Three threads blocking on one futex is a large state space; bounding
+// preemptions keeps it quick while still covering the wake-up races.
+#[attr::concurrent_test(preemption_bound = 2)]
+fn futex_wake_all() {
+    let state = Arc::new(AtomicU32::new(0));
+
+    let waiters: Vec<_> = (0..2)
+        .map(|_| {
+            let state = state.clone();
+            thread::spawn(move || {
+                while state.load(Acquire) == 0 {
+                    wait(&state, 0);
+                }
+            })
+        })
+        .collect();
+
+    state.store(1, Release);
+    wake_all(&state);
+
+    for waiter in waiters {
+        waiter.join().unwrap();
+    }
+}
+
 #[test]
 fn safe_channel() {
     concurrent_test!({
@@ -179,8 +693,7 @@ fn safe_channel() {
     });
 }
 
-#[test]
-#[cfg(not(loom))]
+#[attr::concurrent_test(std_only)]
 fn test_oneshot_basic() {
     // Create a new channel
 
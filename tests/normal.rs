@@ -1,3 +1,5 @@
+#![cfg(not(any(loom, shuttle)))]
+
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use std::{sync::Arc, thread};
@@ -244,47 +246,54 @@ fn rwlock_writer_to_reader_downgrade() {
 
 #[test]
 fn rwlock_stress_test() {
+    use rust_atomics::concurrent_test;
     use rust_atomics::locks::RwLock;
+    use rust_atomics::model::Scheduler;
+    use rust_atomics::sync::{Arc, thread};
 
     const NUM_THREADS: usize = 10;
     const OPS_PER_THREAD: usize = 100;
 
-    let lock = Arc::new(RwLock::new(0));
-    let mut handles = Vec::with_capacity(NUM_THREADS);
-
-    // Create multiple threads that alternate between reading and writing
-    for id in 0..NUM_THREADS {
-        let lock = lock.clone();
-
-        let handle = thread::spawn(move || {
-            for i in 0..OPS_PER_THREAD {
-                if (id + i) % 5 == 0 {
-                    // Writer operation
-                    let mut guard = lock.write();
-                    *guard += 1;
-                } else {
-                    // Reader operation
-                    let guard = lock.read();
-                    assert!(*guard >= 0); // Simple validation
+    // PCT draws its scheduling decisions from the seed, which is printed if
+    // the test fails and can be replayed with LOOM_KIT_SEED
+    concurrent_test!(scheduler = Scheduler::Pct { depth: 3 }, iterations = 20, {
+        let lock = Arc::new(RwLock::new(0));
+        let mut handles = Vec::with_capacity(NUM_THREADS);
+
+        // Create multiple threads that alternate between reading and writing
+        for id in 0..NUM_THREADS {
+            let lock = lock.clone();
+
+            let handle = thread::spawn(move || {
+                for i in 0..OPS_PER_THREAD {
+                    if (id + i) % 5 == 0 {
+                        // Writer operation
+                        let mut guard = lock.write();
+                        *guard += 1;
+                    } else {
+                        // Reader operation
+                        let guard = lock.read();
+                        assert!(*guard <= NUM_THREADS * OPS_PER_THREAD); // Simple validation
+                    }
                 }
-            }
-        });
+            });
 
-        handles.push(handle);
-    }
+            handles.push(handle);
+        }
 
-    // Wait for all threads to complete
-    for handle in handles {
-        handle.join().unwrap();
-    }
+        // Wait for all threads to complete
+        for handle in handles {
+            handle.join().unwrap();
+        }
 
-    // Final check - read the final value
-    let final_value = *lock.read();
+        // Final check - read the final value
+        let final_value = *lock.read();
 
-    // The final value should be equal to the total number of write operations
-    // NUM_THREADS * OPS_PER_THREAD / 5 (since we do a write every 5 operations)
-    let expected_writes = NUM_THREADS * OPS_PER_THREAD / 5;
-    assert_eq!(final_value, expected_writes);
+        // The final value should be equal to the total number of write operations
+        // NUM_THREADS * OPS_PER_THREAD / 5 (since we do a write every 5 operations)
+        let expected_writes = NUM_THREADS * OPS_PER_THREAD / 5;
+        assert_eq!(final_value, expected_writes);
+    });
 }
 
 #[test]
@@ -335,3 +344,25 @@ fn rwlock_read_timeout_consistency() {
     writer.join().unwrap();
     reader1.join().unwrap();
 }
+
+#[test]
+fn locks_in_statics() {
+    use rust_atomics::locks::{Mutex, RwLock};
+    static COUNT: Mutex<usize> = Mutex::new(0);
+    static TOTAL: RwLock<usize> = RwLock::new(0);
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            thread::spawn(|| {
+                *COUNT.lock() += 1;
+                *TOTAL.write() += 10;
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert_eq!(*COUNT.lock(), 4);
+    assert_eq!(*TOTAL.read(), 40);
+}
@@ -0,0 +1,64 @@
+use rust_atomics::rng::{self, Rng};
+
+#[test]
+fn same_seed_same_numbers() {
+    let numbers = |seed| {
+        let mut rng = Rng::new(seed);
+        (0..8).map(|_| rng.next_u64()).collect::<Vec<_>>()
+    };
+    assert_eq!(numbers(7), numbers(7));
+    assert_ne!(numbers(7), numbers(8));
+
+    let mut rng = Rng::new(7);
+    assert!((0..100).all(|_| rng.below(3) < 3));
+}
+
+#[test]
+fn streams_follow_the_run_seed() {
+    let first = |n| rng::stream(n).next_u64();
+
+    // Outside a run the seed is 0, so streams are fixed
+    assert_eq!(rng::current(), None);
+    assert_eq!(first(1), first(1));
+    assert_ne!(first(1), first(2));
+
+    let in_run = |seed| rng::run(seed, || (first(1), rng::current()));
+    assert_eq!(in_run(42), in_run(42));
+    assert_eq!(in_run(42).1, Some(42));
+    assert_ne!(in_run(42).0, in_run(43).0);
+    assert_eq!(rng::current(), None);
+}
+
+#[test]
+#[cfg(not(any(loom, shuttle)))]
+fn seed_replays_a_std_run() {
+    use rust_atomics::model::{Builder, Scheduler};
+    use rust_atomics::sync::thread;
+    use std::sync::{Arc, Mutex};
+
+    // What each iteration's spawned thread draws, for a given seed
+    let draws = |seed, scheduler| {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let record = seen.clone();
+        Builder::new()
+            .seed(seed)
+            .scheduler(scheduler)
+            .iterations(5)
+            .check(move || {
+                let record = record.clone();
+                thread::spawn(move || record.lock().unwrap().push(rng::stream(0).next_u64()))
+                    .join()
+                    .unwrap();
+            });
+        Arc::into_inner(seen).unwrap().into_inner().unwrap()
+    };
+
+    for scheduler in [Scheduler::Random, Scheduler::Pct { depth: 2 }] {
+        let run = draws(1, scheduler);
+        assert_eq!(run, draws(1, scheduler));
+        assert_ne!(run, draws(2, scheduler));
+
+        // Iterations don't all repeat the same decisions
+        assert_ne!(run[0], run[1]);
+    }
+}
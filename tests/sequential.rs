@@ -0,0 +1,83 @@
+mod common;
+
+#[cfg(loom)]
+use common::RacyCounter;
+use common::{Channel, LockedCounter, Queue, QueueOp, RwCounter, SpinCounter};
+use rust_atomics::rng::Rng;
+use rust_atomics::sequential::{self, Program, Shape, Target};
+
+#[test]
+fn programs_that_can_deadlock_are_rejected() {
+    use QueueOp::{Receive, Send};
+
+    let program = |threads: &[&[QueueOp]]| Program {
+        threads: threads.iter().map(|ops| ops.to_vec()).collect(),
+    };
+
+    assert!(program(&[&[Send(1)], &[Receive]]).always_finishes(&Queue::default()));
+    assert!(
+        program(&[&[Send(1), Receive], &[Send(2), Receive]]).always_finishes(&Queue::default())
+    );
+
+    // More receives than sends
+    assert!(!program(&[&[Send(1)], &[Receive, Receive]]).always_finishes(&Queue::default()));
+    // Both threads may receive first
+    assert!(
+        !program(&[&[Receive, Send(1)], &[Receive, Send(2)]]).always_finishes(&Queue::default())
+    );
+}
+
+#[test]
+fn generated_programs_follow_the_seed() {
+    let shape = Shape {
+        programs: 1,
+        threads: 3,
+        ops: 4,
+    };
+    let generate = |seed| {
+        Program::generate(
+            shape,
+            &Queue::default(),
+            &mut Rng::new(seed),
+            Channel::arbitrary_op,
+        )
+    };
+
+    let program = generate(7);
+    assert_eq!(program, generate(7));
+    assert_eq!(program.threads.len(), 3);
+    assert!(program.threads.iter().all(|ops| ops.len() == 4));
+    assert!(program.always_finishes(&Queue::default()));
+}
+
+#[test]
+fn safe_channel_matches_a_queue() {
+    sequential::check_programs::<Channel>();
+}
+
+#[test]
+fn mutex_matches_a_counter() {
+    sequential::check_programs::<LockedCounter>();
+}
+
+#[test]
+fn spin_lock_matches_a_counter() {
+    sequential::check_programs::<SpinCounter>();
+}
+
+#[test]
+fn rwlock_matches_a_counter() {
+    sequential::check_programs::<RwCounter>();
+}
+
+#[test]
+#[cfg(loom)]
+fn racy_counter_does_not_match() {
+    let result = std::panic::catch_unwind(sequential::check_programs::<RacyCounter>);
+    let payload = result.unwrap_err();
+    let message = payload.downcast_ref::<String>().unwrap();
+    assert!(
+        message.starts_with("history is not linearizable"),
+        "{message}"
+    );
+}
@@ -0,0 +1,35 @@
+mod common;
+
+#[cfg(loom)]
+use common::RacyCounter;
+use common::{Channel, RwCounter};
+use proptest::prelude::*;
+use rust_atomics::model::Builder;
+use rust_atomics::sequential::{Shape, strategy};
+
+#[test]
+fn proptest_checks_rwlock() {
+    strategy::proptest_check_programs::<RwCounter>();
+}
+
+#[test]
+#[cfg(loom)]
+fn proptest_shrinks_racy_counter() {
+    let result = std::panic::catch_unwind(strategy::proptest_check_programs::<RacyCounter>);
+    let payload = result.unwrap_err();
+    let message = payload.downcast_ref::<String>().unwrap();
+    assert!(
+        message
+            .starts_with("minimal failing program:\n  thread 0: [Add(1)]\n  thread 1: [Add(1)]\n"),
+        "{message}"
+    );
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(20))]
+
+    #[test]
+    fn safe_channel_in_a_proptest(program in strategy::programs::<Channel>(Shape::default())) {
+        Builder::new().check_program::<Channel>(&program);
+    }
+}
@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use rust_atomics::stats::Stats;
+
+fn stats() -> Stats {
+    Stats {
+        test: Some("stats::example".to_string()),
+        timestamp: 1_700_000_000,
+        executions: 226,
+        max_threads: Some(3),
+        max_branches: Some(43),
+        max_preemptions: Some(2),
+        preemption_bound: Some(2),
+        bound_reached: Some(true),
+        complete: true,
+        wall_time: Duration::from_millis(17),
+    }
+}
+
+#[test]
+fn stats_round_trip() {
+    let dir = std::env::temp_dir().join(format!("loom-kit-stats-{}", std::process::id()));
+    let path = dir.join("stats.jsonl");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let first = stats();
+    let second = Stats {
+        executions: 6,
+        complete: false,
+        preemption_bound: None,
+        ..stats()
+    };
+    first.append(&path).unwrap();
+    second.append(&path).unwrap();
+    assert_eq!(Stats::read_all(&path).unwrap(), [first, second]);
+
+    // The wall time is stored as seconds
+    let json = std::fs::read_to_string(&path).unwrap();
+    assert!(
+        json.lines()
+            .next()
+            .unwrap()
+            .contains(r#""wall_time_secs":0.017"#)
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn stats_display() {
+    assert_eq!(
+        stats().to_string(),
+        "226 executions in 17.00ms; at most 3 threads, 43 branches and 2 preemptions; \
+         the preemption bound of 2 was reached and may have pruned interleavings"
+    );
+
+    let unbounded = Stats {
+        preemption_bound: None,
+        complete: false,
+        ..stats()
+    };
+    assert_eq!(
+        unbounded.to_string(),
+        "226 executions in 17.00ms (incomplete); at most 3 threads, 43 branches and 2 preemptions"
+    );
+
+    // Without the counts, only the cheap figures
+    let uncounted = Stats {
+        max_threads: None,
+        max_branches: None,
+        max_preemptions: None,
+        bound_reached: None,
+        ..stats()
+    };
+    assert_eq!(
+        uncounted.to_string(),
+        "226 executions in 17.00ms with a preemption bound of 2"
+    );
+}
+
+#[test]
+#[cfg(loom)]
+fn stats_match_loom() {
+    use rust_atomics::model::Builder;
+    use rust_atomics::stats;
+    use rust_atomics::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+    use rust_atomics::sync::{Arc, thread};
+
+    let model = || {
+        let value = Arc::new(AtomicUsize::new(0));
+        let writer = {
+            let value = value.clone();
+            thread::spawn(move || value.store(1, Relaxed))
+        };
+        value.load(Relaxed);
+        writer.join().unwrap();
+    };
+
+    for bound in 0..3 {
+        Builder::new()
+            .preemption_bound(bound)
+            .stats(true)
+            .check(model);
+        let stats = stats::last().unwrap();
+        assert!(stats.complete);
+        assert_eq!(stats.max_threads, Some(2));
+        assert_eq!(stats.preemption_bound, Some(bound));
+        assert!(stats.max_preemptions.unwrap() <= bound);
+        assert_eq!(stats.bound_reached, Some(true));
+    }
+
+    // Loom panics on more than `max_branches` branches, so the count is
+    // the lowest limit the model passes with
+    Builder::new().stats(true).check(model);
+    let stats = stats::last().unwrap();
+    assert_eq!(stats.max_branches, Some(10));
+    assert_eq!(stats.executions, 6);
+    assert_eq!(stats.bound_reached, Some(false));
+    Builder::new().max_branches(10).check(model);
+}
+
+#[test]
+#[cfg(loom)]
+fn counts_are_opt_in() {
+    use rust_atomics::model::Builder;
+    use rust_atomics::stats::{self, STATS_VAR};
+
+    if std::env::var_os(STATS_VAR).is_some() {
+        return;
+    }
+    Builder::new().preemption_bound(1).check(|| {});
+    let stats = stats::last().unwrap();
+    assert_eq!(stats.executions, 1);
+    assert_eq!(stats.preemption_bound, Some(1));
+    assert_eq!(stats.max_threads, None);
+    assert_eq!(stats.bound_reached, None);
+
+    Builder::new().stats(true).check(|| {});
+    assert_eq!(stats::last().unwrap().max_threads, Some(1));
+}
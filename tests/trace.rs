@@ -0,0 +1,182 @@
+use rust_atomics::trace::{self, Event, LockMode, Object, Op};
+
+/// The end of `loom-kit trace release_and_acquire_incorrect`, colours and
+/// all, without the backtrace.
+const RELEASE_AND_ACQUIRE: &str = include_str!("fixtures/release_and_acquire_incorrect.log");
+
+fn atomic(id: usize) -> Object {
+    Object {
+        kind: "atomic".to_string(),
+        id,
+    }
+}
+
+#[test]
+fn parse_failing_execution() {
+    let executions = trace::parse(RELEASE_AND_ACQUIRE);
+    assert_eq!(
+        executions
+            .iter()
+            .map(|execution| execution.iteration)
+            .collect::<Vec<_>>(),
+        [22, 23]
+    );
+    assert!(executions[0].panic.is_none());
+
+    let execution = trace::failing(&executions).unwrap();
+    assert_eq!(execution.iteration, 23);
+    assert_eq!(execution.threads(), [0, 1]);
+
+    let ops: Vec<(usize, Op)> = execution
+        .events
+        .iter()
+        .map(|event| (event.thread, event.op.clone()))
+        .collect();
+    let relaxed = || "Relaxed".to_string();
+    assert_eq!(
+        ops,
+        [
+            (0, Op::Spawn { thread: 1 }),
+            (
+                1,
+                Op::Store {
+                    object: atomic(0),
+                    ordering: relaxed()
+                }
+            ),
+            (
+                1,
+                Op::Store {
+                    object: atomic(2),
+                    ordering: "Release".to_string()
+                }
+            ),
+            (1, Op::Done),
+            // Thread 0 reached this load before thread 1 ran, but only
+            // performs it once it is scheduled again
+            (
+                0,
+                Op::Load {
+                    object: atomic(2),
+                    ordering: relaxed()
+                }
+            ),
+            (
+                0,
+                Op::Load {
+                    object: atomic(0),
+                    ordering: relaxed()
+                }
+            ),
+        ]
+    );
+
+    let panic = execution.panic.as_ref().unwrap();
+    assert_eq!(panic.thread, Some(0));
+    assert_eq!(panic.location, "tests/loom.rs:139:9");
+    assert_eq!(
+        panic.message,
+        "assertion `left == right` failed\n  left: 42\n right: 0"
+    );
+    assert_eq!(panic.after, 6);
+}
+
+#[test]
+fn timeline_marks_the_failing_step() {
+    let executions = trace::parse(RELEASE_AND_ACQUIRE);
+    let timeline = trace::failing(&executions).unwrap().timeline().to_string();
+
+    let expected = "\
+iteration 23
+   step | thread 0              | thread 1
+      0 | spawn thread 1        |
+      1 |                       | store atomic#0 Relaxed
+      2 |                       | store atomic#2 Release
+      3 |                       | done
+      4 | load atomic#2 Relaxed |
+>>    5 | load atomic#0 Relaxed |
+
+thread 0 panicked at tests/loom.rs:139:9:
+  assertion `left == right` failed
+    left: 42
+   right: 0
+";
+    assert_eq!(timeline, expected);
+}
+
+#[test]
+fn lock_is_recorded_once_held() {
+    let log = "\
+ INFO loom::model:  ================== Iteration 1 ==================
+TRACE iter{1}:thread{id=1}: loom::rt::object: Object::branch_acquire obj=Ref<loom::rt::mutex::State>(3) is_locked=true
+TRACE iter{1}:thread{id=1}: loom::rt: branch switch=true
+ INFO iter{1}:thread{id=0}: loom::rt::execution: ~~~~~~~~ THREAD 0 ~~~~~~~~
+TRACE iter{1}:thread{id=0}: loom::rt::atomic: Atomic::rmw state=Ref<loom::rt::atomic::State>(0) success=AcqRel failure=Acquire
+TRACE iter{1}:thread{id=0}: loom::rt: thread_done: drop locals thread=Id(0)
+ INFO iter{1}:thread{id=1}: loom::rt::execution: ~~~~~~~~ THREAD 1 ~~~~~~~~
+TRACE iter{1}:thread{id=1}: loom::rt: synchronize
+TRACE iter{1}:thread{id=1}: loom::rt::arc: Arc::new state=Ref<loom::rt::arc::State>(4) location=src/lib.rs:1:1
+";
+    let executions = trace::parse(log);
+    let events = &executions[0].events;
+
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0].thread, 0);
+    assert_eq!(events[0].to_string(), "rmw atomic#0 AcqRel/Acquire");
+    assert_eq!(events[1].op, Op::Done);
+    assert_eq!(
+        events[2],
+        Event {
+            thread: 1,
+            op: Op::Lock {
+                object: Object {
+                    kind: "mutex".to_string(),
+                    id: 3
+                },
+                mode: LockMode::Exclusive,
+                contended: true,
+            },
+            location: None,
+        }
+    );
+    assert_eq!(events[2].to_string(), "lock mutex#3 (contended)");
+    assert!(executions[0].panic.is_none());
+}
+
+#[test]
+fn cells_are_numbered_by_first_borrow() {
+    let log = "\
+ INFO loom::model:  ================== Iteration 1 ==================
+TRACE iter{1}:thread{id=0}: rust_atomics::sync: UnsafeCell::with_mut cell=0x5610c0a0
+TRACE iter{1}:thread{id=1}: rust_atomics::sync: UnsafeCell::with cell=0x5610c0b8
+TRACE iter{1}:thread{id=1}: rust_atomics::sync: UnsafeCell::with cell=0x5610c0a0
+ INFO loom::model:  ================== Iteration 2 ==================
+TRACE iter{2}:thread{id=1}: rust_atomics::sync: UnsafeCell::with cell=0x5610c0b8
+";
+    let executions = trace::parse(log);
+    let cell = |id| Object {
+        kind: "cell".to_string(),
+        id,
+    };
+
+    let ops: Vec<&Op> = executions[0].events.iter().map(|event| &event.op).collect();
+    assert_eq!(
+        ops,
+        [
+            &Op::Write { object: cell(0) },
+            &Op::Read { object: cell(1) },
+            &Op::Read { object: cell(0) },
+        ]
+    );
+    assert_eq!(executions[0].events[0].to_string(), "write cell#0");
+    assert_eq!(executions[0].events[1].to_string(), "read cell#1");
+    assert_eq!(executions[0].events[1].op.object(), Some(&cell(1)));
+    // Each execution numbers its cells afresh
+    assert_eq!(executions[1].events[0].op, Op::Read { object: cell(0) });
+}
+
+#[test]
+fn nothing_to_parse() {
+    assert!(trace::parse("running 0 tests\n").is_empty());
+    assert!(trace::failing(&[]).is_none());
+}
@@ -0,0 +1,64 @@
+use rust_atomics::checkpoint::Checkpoint;
+use rust_atomics::trace;
+use rust_atomics::viewer;
+
+#[test]
+fn page_embeds_the_failing_execution() {
+    let executions = trace::parse(include_str!("fixtures/release_and_acquire_incorrect.log"));
+    let checkpoint: Checkpoint = include_str!("fixtures/release_and_acquire_incorrect.json")
+        .parse()
+        .unwrap();
+    let html = viewer::render(
+        "release_and_acquire_incorrect",
+        trace::failing(&executions).unwrap(),
+        Some(&checkpoint),
+    );
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<h1>release_and_acquire_incorrect</h1>"));
+    assert!(html.contains("\"threads\":[0,1]"), "{html}");
+    assert!(
+        html.contains(
+            r#"{"failed":true,"object":"atomic#0","op":"load atomic#0 Relaxed","thread":0}"#
+        ),
+        "{html}"
+    );
+    assert!(html.contains("Iteration 23: 10 branch points"), "{html}");
+    assert!(html.contains("panicked at tests/loom.rs:139:9"), "{html}");
+
+    // Everything is inline, so the page works offline
+    assert!(!html.contains(" src="));
+    assert!(!html.contains("http"));
+}
+
+#[test]
+fn page_escapes_its_input() {
+    let log = "\
+ INFO loom::model:  ================== Iteration 1 ==================
+TRACE iter{1}:thread{id=0}: loom::rt: yield_now thread=Id(0) switch=true
+thread 'main' (1) panicked at src/lib.rs:1:1:
+</script><b>
+";
+    let executions = trace::parse(log);
+    let html = viewer::render("a<b>", &executions[0], None);
+
+    assert!(html.contains("<h1>a&lt;b&gt;</h1>"));
+    assert_eq!(html.matches("</script>").count(), 1);
+    assert!(html.contains("<\\/script><b>"), "{html}");
+}
+
+#[test]
+fn page_shows_cell_borrows() {
+    let log = "\
+ INFO loom::model:  ================== Iteration 1 ==================
+TRACE iter{1}:thread{id=0}: loom::rt::atomic: Atomic::load state=Ref<loom::rt::atomic::State>(0) ordering=Acquire
+TRACE iter{1}:thread{id=0}: rust_atomics::sync: UnsafeCell::with cell=0x5610c0a0
+";
+    let executions = trace::parse(log);
+    let html = viewer::render("cell", &executions[0], None);
+
+    assert!(
+        html.contains(r#"{"failed":false,"object":"cell#0","op":"read cell#0","thread":0}"#),
+        "{html}"
+    );
+}
@@ -0,0 +1,99 @@
+#![cfg(not(any(loom, shuttle)))]
+
+use std::env;
+use std::process::Command;
+use std::time::Duration;
+
+use rust_atomics::channels::safe::SafeChannel;
+use rust_atomics::concurrent_test;
+use rust_atomics::locks::Mutex;
+use rust_atomics::sync::{Arc, Barrier, thread};
+
+/// Deadlocks on purpose, run in a process of its own by
+/// `deadlock_is_reported_with_where_each_thread_waits`, as the watchdog
+/// exits the process.
+#[test]
+#[ignore = "deadlocks; run by deadlock_is_reported_with_where_each_thread_waits"]
+fn deadlocked_body() {
+    concurrent_test!(deadlock_timeout = Duration::from_millis(300), seed = 7, {
+        let lock = Arc::new(Mutex::new(()));
+        let channel = Arc::new(SafeChannel::<()>::new());
+        let locked = Arc::new(Barrier::new(2));
+
+        // Holds the lock while waiting for a message that never comes
+        let receiver = {
+            let (lock, channel, locked) = (lock.clone(), channel.clone(), locked.clone());
+            thread::spawn(move || {
+                let _guard = lock.lock();
+                locked.wait();
+                channel.receive();
+            })
+        };
+        locked.wait();
+        let locker = thread::spawn(move || drop(lock.lock()));
+
+        locker.join().unwrap();
+        receiver.join().unwrap();
+    });
+}
+
+#[test]
+fn deadlock_is_reported_with_where_each_thread_waits() {
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["deadlocked_body", "--exact", "--ignored"])
+        .output()
+        .unwrap();
+    let message = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(101), "{message}");
+    assert!(
+        message.contains("deadlock watchdog: iteration 1 didn't finish within 300ms"),
+        "{message}"
+    );
+    assert!(message.contains("thread 'deadlocked_body'"), "{message}");
+    assert!(
+        message.contains("running, or blocked outside the crate's wait points"),
+        "{message}"
+    );
+    assert!(
+        message.contains("blocked in SafeChannel::receive at tests/watchdog.rs:"),
+        "{message}"
+    );
+    assert!(
+        message.contains("blocked in Mutex::lock at tests/watchdog.rs:"),
+        "{message}"
+    );
+    assert!(
+        message.contains("failed with seed 7; rerun with LOOM_KIT_SEED=7"),
+        "{message}"
+    );
+}
+
+#[test]
+fn slow_iterations_each_get_the_whole_timeout() {
+    concurrent_test!(
+        deadlock_timeout = Duration::from_millis(300),
+        iterations = 3,
+        {
+            std::thread::sleep(Duration::from_millis(200));
+        }
+    );
+}
+
+#[test]
+fn panics_in_the_body_are_passed_on() {
+    concurrent_test!(expected_panic = "from the body", {
+        panic!("from the body");
+    });
+}
+
+#[test]
+fn bodies_run_on_the_test_thread_and_borrow_locals() {
+    let test = std::thread::current().id();
+    let runs = std::cell::Cell::new(0);
+    concurrent_test!(iterations = 3, {
+        assert_eq!(std::thread::current().id(), test);
+        runs.set(runs.get() + 1);
+    });
+    assert_eq!(runs.get(), 3);
+}